@@ -0,0 +1,290 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use ogam::stats::Digest;
+use serde_json::{json, Value};
+
+use libceltchar::{Diagnostic, Error, Loader, Project};
+
+use crate::filesystem::{canonicalize_documents, read_project, Fs, PROJECT_FILE};
+
+const LANGUAGES: &[&str] = &["En", "Fr"];
+
+pub enum Format {
+    Human,
+    Json,
+}
+
+struct Issue {
+    error: bool,
+    kind: String,
+    file: Option<String>,
+    message: String,
+    diagnostic: Option<Diagnostic>,
+}
+
+impl Issue {
+    fn new(error: bool, kind: &str, file: Option<&Path>, message: String) -> Issue {
+        Issue {
+            error,
+            kind: String::from(kind),
+            file: file.map(|f| f.display().to_string()),
+            message,
+            diagnostic: None,
+        }
+    }
+
+    fn from_diagnostic(error: bool, diagnostic: Diagnostic) -> Issue {
+        let kind = serde_json::to_value(diagnostic.kind)
+            .ok()
+            .and_then(|k| k.as_str().map(String::from))
+            .unwrap_or_default();
+
+        Issue {
+            error,
+            kind,
+            file: Some(diagnostic.document.clone()),
+            message: diagnostic.to_string(),
+            diagnostic: Some(diagnostic),
+        }
+    }
+
+    fn from_error(err: &Error) -> Issue {
+        let mut message = err.to_string();
+
+        for cause in err.causes() {
+            message.push_str(": ");
+            message.push_str(&cause.to_string());
+        }
+
+        Issue::new(true, "project", None, message)
+    }
+
+    fn to_json(&self) -> Value {
+        json!({
+            "severity": if self.error { "error" } else { "warning" },
+            "kind": self.kind,
+            "file": self.file,
+            "line": self.diagnostic.as_ref().map(|d| d.line),
+            "column": self.diagnostic.as_ref().map(|d| d.column),
+            "message": match self.diagnostic {
+                Some(ref d) => String::from(d.kind.describe()),
+                None => self.message.clone(),
+            },
+            "excerpt": self.diagnostic.as_ref().map(|d| &d.excerpt),
+        })
+    }
+}
+
+impl std::fmt::Display for Issue {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let severity = if self.error { "error" } else { "warning" };
+
+        match (&self.diagnostic, &self.file) {
+            (Some(_), _) => write!(f, "{}: {}", severity, self.message),
+            (None, Some(file)) => write!(f, "{}: {}: {}", severity, file, self.message),
+            (None, None) => write!(f, "{}: {}", severity, self.message),
+        }
+    }
+}
+
+/// Check the language of the project before anything else, since an
+/// unknown language prevents the project file from being parsed at all.
+fn check_language(root: &Path, issues: &mut Vec<Issue>) -> Result<(), Error> {
+    let path = root.join(PROJECT_FILE);
+    let input = fs::read_to_string(&path).map_err(|e| Error::io("read", &path, e))?;
+
+    if let Ok(value) = input.parse::<toml::Value>() {
+        if let Some(lang) = value.get("language").and_then(|l| l.as_str()) {
+            if !LANGUAGES.contains(&lang) {
+                issues.push(Issue::new(
+                    true,
+                    "unknown-language",
+                    Some(Path::new(PROJECT_FILE)),
+                    format!(
+                        "unknown language `{}`, expected one of {}",
+                        lang,
+                        LANGUAGES.join(", ")
+                    ),
+                ));
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn check_cover(root: &Path, cover: &Option<PathBuf>, issues: &mut Vec<Issue>) {
+    match cover {
+        None => issues.push(Issue::new(
+            false,
+            "no-cover",
+            None,
+            String::from("the book has no cover"),
+        )),
+        Some(cover) if !root.join(cover).is_file() => issues.push(Issue::new(
+            true,
+            "missing-cover",
+            Some(cover),
+            String::from("cover file not found"),
+        )),
+        Some(cover) => {
+            if let Err(err) = Fs
+                .load_cover(&root.join(cover))
+                .and_then(|c| c.media_type().map(|_| ()))
+            {
+                issues.push(Issue::new(
+                    true,
+                    "unsupported-cover",
+                    Some(cover),
+                    err.to_string(),
+                ));
+            }
+        }
+    }
+}
+
+fn check_documents(root: &Path, project: &Project<PathBuf, PathBuf>, issues: &mut Vec<Issue>) {
+    let mut seen = HashMap::new();
+
+    for (idx, chapter) in (1..).zip(project.content.chapters()) {
+        let label = match chapter.title {
+            Some(ref title) => format!("chapter {} ({})", idx, title),
+            None => format!("chapter {}", idx),
+        };
+
+        if chapter.content.is_empty() {
+            issues.push(Issue::new(
+                false,
+                "empty-chapter",
+                None,
+                format!("{} has no document", label),
+            ));
+        }
+
+        for file in &chapter.content {
+            let path = root.join(file);
+            // `a.ogam` and `./a.ogam` are the same document
+            let key = path.canonicalize().unwrap_or_else(|_| path.clone());
+
+            if !path.is_file() {
+                issues.push(Issue::new(
+                    true,
+                    "missing-file",
+                    Some(file),
+                    format!("document of {} not found", label),
+                ));
+            } else if let Some(other) = seen.insert(key, label.clone()) {
+                issues.push(Issue::new(
+                    true,
+                    "duplicate-file",
+                    Some(file),
+                    format!("document of {} already used by {}", label, other),
+                ));
+            } else if fs::read_to_string(&path)
+                .map(|x| x.trim().is_empty())
+                .unwrap_or(false)
+            {
+                issues.push(Issue::new(
+                    false,
+                    "empty-chapter",
+                    Some(file),
+                    format!("document of {} is empty", label),
+                ));
+            }
+        }
+    }
+}
+
+/// Render the documents of the project to report their ill-formed content.
+/// The cover is left aside, since `check_cover` already reports its issues.
+fn check_content(
+    root: &Path,
+    project: &Project<PathBuf, PathBuf>,
+    strict: bool,
+    issues: &mut Vec<Issue>,
+) {
+    let mut diagnostics = vec![];
+    let res = canonicalize_documents(root, &project.content).and_then(|content| {
+        content.load_and_render::<_, _, Digest>(
+            &Fs,
+            project.language.typography(),
+            &mut diagnostics,
+        )
+    });
+
+    for diagnostic in diagnostics {
+        issues.push(Issue::from_diagnostic(strict, diagnostic));
+    }
+
+    match res {
+        Ok(_) => (),
+        Err(Error::Parse(diagnostics)) => {
+            for diagnostic in diagnostics {
+                issues.push(Issue::from_diagnostic(true, diagnostic));
+            }
+        }
+        Err(err) => issues.push(Issue::from_error(&err)),
+    }
+}
+
+fn collect_issues(root: &Path, strict: bool) -> Result<Vec<Issue>, Error> {
+    let mut issues = vec![];
+
+    check_language(root, &mut issues)?;
+
+    let project = match read_project(root) {
+        Ok(project) => project,
+        Err(err) => {
+            // An unknown language has already been reported
+            if issues.is_empty() {
+                issues.push(Issue::from_error(&err));
+            }
+            return Ok(issues);
+        }
+    };
+
+    check_cover(root, &project.cover, &mut issues);
+    check_documents(root, &project, &mut issues);
+
+    // Rendering the documents requires them all to be there
+    if !issues.iter().any(|i| i.kind == "missing-file") {
+        check_content(root, &project, strict, &mut issues);
+    }
+
+    Ok(issues)
+}
+
+/// Lint the project found at `root` without building it, and print the
+/// issues found.  Returns the number of errors.  In strict mode, ill-formed
+/// ogam content counts as an error rather than a warning.
+pub fn check(root: &Path, format: Format, strict: bool) -> Result<usize, Error> {
+    let issues = collect_issues(root, strict)?;
+    let errors = issues.iter().filter(|i| i.error).count();
+    let warnings = issues.len() - errors;
+
+    match format {
+        Format::Human => {
+            for issue in &issues {
+                println!("{}\n", issue);
+            }
+
+            println!("{} error(s), {} warning(s)", errors, warnings);
+        }
+        Format::Json => {
+            let report = json!({
+                "errors": errors,
+                "warnings": warnings,
+                "issues": issues.iter().map(Issue::to_json).collect::<Vec<_>>(),
+            });
+
+            println!(
+                "{}",
+                serde_json::to_string_pretty(&report).unwrap_or_default()
+            );
+        }
+    }
+
+    Ok(errors)
+}
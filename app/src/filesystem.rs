@@ -1,11 +1,16 @@
 use std::env::{current_dir, set_current_dir};
 use std::fs;
 use std::fs::canonicalize;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
-use libceltchar::{Chapter, Content, Cover, Error, Loader, Part, Project, Raise};
+use libceltchar::{
+    Chapter, Content, Cover, Error, FontFace, FontFamily, Loader, Part, Project, Raise,
+};
 
-const PROJECT_FILE: &str = "Book.toml";
+pub const PROJECT_FILE: &str = "Book.toml";
+/// Directory of a project where templates overriding the installed ones
+/// are looked up.
+pub const TEMPLATES_DIR: &str = "templates";
 pub struct Fs;
 
 pub fn find_root() -> Result<PathBuf, Error> {
@@ -33,14 +38,38 @@ pub fn find_root() -> Result<PathBuf, Error> {
     }
 }
 
+/// Compute the default path of a file generated for a project: a file
+/// named after the title of the book, at the root of the project.
+pub fn default_output(root: &Path, title: &str, extension: &str) -> PathBuf {
+    let mut name = String::with_capacity(title.len());
+
+    for c in title.chars() {
+        if c.is_alphanumeric() {
+            name.extend(c.to_lowercase());
+        } else if !name.is_empty() && !name.ends_with('-') {
+            name.push('-');
+        }
+    }
+
+    while name.ends_with('-') {
+        name.pop();
+    }
+
+    if name.is_empty() {
+        name.push_str("book");
+    }
+
+    root.join(format!("{}.{}", name, extension))
+}
+
 fn canonicalize_chapter(chapter: &Chapter<PathBuf>) -> Result<Chapter<PathBuf>, Error> {
     let title = chapter.title.clone();
     Ok(Chapter {
-        title: title,
+        title,
         content: chapter
             .content
             .iter()
-            .map(|x| canonicalize(x).or_raise(&format!("Could not canonicalize {:?}", x)))
+            .map(|x| canonicalize(x).map_err(|e| Error::io("find", x, e)))
             .collect::<Result<_, Error>>()?,
     })
 }
@@ -74,6 +103,26 @@ fn canonicalize_content(content: &Content<PathBuf>) -> Result<Content<PathBuf>,
     }
 }
 
+/// Font files found relative to the project root take precedence over the
+/// ones shipped with the assets, which are left untouched.
+fn canonicalize_fonts(fonts: Vec<FontFamily>) -> Vec<FontFamily> {
+    fonts
+        .into_iter()
+        .map(|family| FontFamily {
+            family: family.family,
+            faces: family
+                .faces
+                .into_iter()
+                .map(|face| FontFace {
+                    file: canonicalize(&face.file).unwrap_or(face.file),
+                    weight: face.weight,
+                    style: face.style,
+                })
+                .collect(),
+        })
+        .collect()
+}
+
 fn canonicalize_project(
     project: Project<PathBuf, PathBuf>,
 ) -> Result<Project<PathBuf, PathBuf>, Error> {
@@ -83,36 +132,66 @@ fn canonicalize_project(
         description: project.description,
         cover: project
             .cover
-            .map(canonicalize)
-            .map_or(Ok(None), |r| r.map(Some))
-            .or_raise("…")?,
+            .map(|x| canonicalize(&x).map_err(|e| Error::io("find", x, e)))
+            .map_or(Ok(None), |r| r.map(Some))?,
         numbering: project.numbering,
         content: canonicalize_content(&project.content)?,
         language: project.language,
+        epub_version: project.epub_version,
+        metadata: project.metadata,
+        fonts: project.fonts.map(canonicalize_fonts),
+        subset_fonts: project.subset_fonts,
+        characters: project.characters,
+        epub_character_styles: project.epub_character_styles,
     })
 }
 
+/// Parse the project file of the project found at `root`, leaving the paths
+/// it contains as they are written.
+pub fn read_project(root: &Path) -> Result<Project<PathBuf, PathBuf>, Error> {
+    let path = root.join(PROJECT_FILE);
+    let input = fs::read_to_string(&path).map_err(|e| Error::io("read", &path, e))?;
+
+    toml::from_str(input.as_str()).map_err(|e: toml::de::Error| Error::Project {
+        location: e.line_col().map(|(line, col)| (line + 1, col + 1)),
+        path,
+        source: Box::new(e),
+    })
+}
+
+/// Run `f` from the root of a project, so that the paths of its project
+/// file can be canonicalized.
+fn in_project<T, F>(root: &Path, f: F) -> Result<T, Error>
+where
+    F: FnOnce() -> Result<T, Error>,
+{
+    let cwd = current_dir().or_raise("could not get current dir")?;
+
+    set_current_dir(root).map_err(|e| Error::io("enter", root, e))?;
+    let res = f();
+    set_current_dir(&cwd).map_err(|e| Error::io("enter", &cwd, e))?;
+
+    res
+}
+
+/// Canonicalize the paths of the documents of a project found at `root`,
+/// leaving its cover and fonts aside.
+pub fn canonicalize_documents(
+    root: &Path,
+    content: &Content<PathBuf>,
+) -> Result<Content<PathBuf>, Error> {
+    in_project(root, || canonicalize_content(content))
+}
+
 impl Loader for Fs {
     type ProjId = PathBuf;
     type CovId = PathBuf;
     type DocId = PathBuf;
 
     fn load_project(&self, id: &PathBuf) -> Result<Project<PathBuf, PathBuf>, Error> {
-        let cwd = current_dir().or_raise("could not get current dir")?;
-
-        let input =
-            fs::read_to_string(PROJECT_FILE).or_raise("found Book.toml, but cannot read it")?;
-
-        // We have to modify set the current directory to the PROJECT_FILE directory,
-        // otherwise `canonicalize` will not work.
-        set_current_dir(id).or_raise("could not change the current directory")?;
-        let res = canonicalize_project(
-            toml::from_str(input.as_str())
-                .map_err(|e| Error(format!("Could not parse Book.toml: {}", e)))?,
-        )?;
-        set_current_dir(cwd).or_raise("could not change the current directory")?;
+        let project = read_project(id)?;
 
-        Ok(res)
+        in_project(id, || canonicalize_project(project))
     }
 
     fn load_cover(&self, id: &PathBuf) -> Result<Cover, Error> {
@@ -122,15 +201,23 @@ impl Loader for Fs {
             .to_str()
             .or_raise("cover extension is not valid utf-8")?;
 
-        let content = fs::read(id).or_raise(&format!("could not read cover from {:?}", id))?;
+        let content = fs::read(id).map_err(|e| Error::io("read", id, e))?;
 
         Ok(Cover {
             extension: String::from(extension),
-            content: content,
+            content,
         })
     }
 
     fn load_document(&self, id: &PathBuf) -> Result<String, Error> {
-        fs::read_to_string(id).or_raise(&format!("Could not read {:?}", id))
+        fs::read_to_string(id).map_err(|e| Error::io("read", id, e))
+    }
+
+    fn document_name(&self, id: &PathBuf) -> String {
+        let relative = current_dir()
+            .ok()
+            .and_then(|cwd| id.strip_prefix(cwd).ok().map(PathBuf::from));
+
+        relative.unwrap_or_else(|| id.clone()).display().to_string()
     }
 }
@@ -5,21 +5,26 @@ extern crate serde_json;
 extern crate tera;
 extern crate toml;
 
-use std::path::PathBuf;
+use std::fs::File;
+use std::path::{Path, PathBuf};
 
-use clap::{App, Arg, SubCommand};
+use clap::{App, AppSettings, Arg, SubCommand};
 
-use libceltchar::{Chapter, Content, EpubWriter, Error, Loader, Part, Project, Static, Zip};
-
-#[cfg(debug_assertions)]
-use libceltchar::Raise;
-#[cfg(debug_assertions)]
-use std::env::current_dir;
+use libceltchar::{
+    source_date_epoch, validate_epub, Assets, Chapter, Content, Cover, Directory, DocxWriter,
+    Embedded, EpubVersion, EpubWriter, Error, Fb2Writer, Language, LatexWriter, Loader,
+    MarkdownWriter, OdtWriter, Part, Project, Raise, Static, TextWriter, Zip,
+};
 
+use ogam::generator::Output;
 use ogam::stats::Digest;
 
+mod check;
 mod filesystem;
-use crate::filesystem::{find_root, Fs};
+mod scaffold;
+use crate::check::{check, Format};
+use crate::filesystem::{default_output, find_root, Fs, TEMPLATES_DIR};
+use crate::scaffold::{export_templates, new_project, Layout};
 
 fn deps() -> Result<(), Error> {
     let root = find_root()?;
@@ -39,31 +44,228 @@ fn deps() -> Result<(), Error> {
     Ok(())
 }
 
-fn build_epub(assets: &PathBuf) -> Result<(), Error> {
-    let root = find_root()?;
-    let loader = Fs;
+/// Load and render the project found at `root`.  Ill-formed content is
+/// reported as warnings, or fails the build in strict mode.
+fn render_project<O: Output>(root: &Path, strict: bool) -> Result<Project<Cover, O>, Error> {
+    let (project, diagnostics) =
+        Project::load_and_render_with_diagnostics(&root.to_path_buf(), &Fs)?;
+
+    if strict && !diagnostics.is_empty() {
+        return Err(Error::Parse(diagnostics));
+    }
+
+    for diagnostic in diagnostics {
+        eprintln!("warning: {}\n", diagnostic);
+    }
 
-    let project = Project::load_and_render(&root, &loader)?;
+    Ok(project)
+}
 
-    let mut zip_writer = Zip::init()?;
-    zip_writer.generate_epub(&project, assets)?;
+/// Write the archive `out` with `generate`, with reproducible timestamps if
+/// `SOURCE_DATE_EPOCH` is set.
+fn write_zip<F>(out: &Path, generate: F) -> Result<(), Error>
+where
+    F: FnOnce(&mut Zip<File>) -> Result<(), Error>,
+{
+    let mut zip_writer = Zip::create(out)?;
+
+    if let Some(epoch) = source_date_epoch()? {
+        zip_writer = zip_writer.reproducible(epoch);
+    }
+    generate(&mut zip_writer)?;
+    zip_writer.finish()?;
 
     Ok(())
 }
 
-fn build_static(assets: &PathBuf, body_only: bool, out: &PathBuf) -> Result<(), Error> {
+fn build_epub(
+    assets: &dyn Assets,
+    version: Option<EpubVersion>,
+    subset_fonts: bool,
+    strict: bool,
+    out: Option<PathBuf>,
+) -> Result<PathBuf, Error> {
     let root = find_root()?;
-    let loader = Fs;
 
-    let project = Project::load_and_render(&root, &loader)?;
+    let mut project = render_project(&root, strict)?;
+
+    if version.is_some() {
+        project.epub_version = version;
+    }
+
+    if subset_fonts {
+        project.subset_fonts = Some(true);
+    }
+
+    let out = out.unwrap_or_else(|| default_output(&root, &project.title, "epub"));
+
+    write_zip(&out, |zip_writer| {
+        zip_writer.generate_epub(&project, assets, Some(&root.join(TEMPLATES_DIR)))
+    })?;
+
+    Ok(out)
+}
+
+/// Print the violations of the EPUB specification found in an archive, and
+/// tell whether there was none.
+fn validate(path: &Path) -> Result<bool, Error> {
+    let file = File::open(path).map_err(|e| Error::io("open", path, e))?;
+    let violations = validate_epub(file)?;
+
+    for violation in &violations {
+        eprintln!("{}: {}", path.display(), violation);
+    }
+
+    Ok(violations.is_empty())
+}
+
+fn build_static(
+    assets: &dyn Assets,
+    body_only: bool,
+    strict: bool,
+    out: &Path,
+) -> Result<(), Error> {
+    let root = find_root()?;
+
+    let project = render_project(&root, strict)?;
 
     let mut static_website = Static::init(out, body_only)?;
-    static_website.generate_static_website(&project, assets)?;
+    static_website.generate_static_website(&project, assets, Some(&root.join(TEMPLATES_DIR)))?;
+
+    Ok(())
+}
+
+/// Split the path of an output file into the directory to write in, which
+/// is the current one for a bare file name, and the name of the file.
+fn split_output(out: &Path) -> Result<(PathBuf, PathBuf), Error> {
+    let name = out
+        .file_name()
+        .map(PathBuf::from)
+        .or_raise("Invalid output file")?;
+    let base = match out.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent.to_path_buf(),
+        _ => PathBuf::from("."),
+    };
+
+    Ok((base, name))
+}
+
+fn build_single_page(
+    assets: &dyn Assets,
+    strict: bool,
+    out: Option<PathBuf>,
+) -> Result<PathBuf, Error> {
+    let root = find_root()?;
+
+    let project = render_project(&root, strict)?;
+
+    let out = out.unwrap_or_else(|| default_output(&root, &project.title, "html"));
+    let (base, name) = split_output(&out)?;
+
+    let mut static_website = Static::init(&base, false)?;
+    static_website.generate_single_page(
+        &project,
+        assets,
+        Some(&root.join(TEMPLATES_DIR)),
+        &name,
+    )?;
+
+    Ok(out)
+}
+
+fn build_markdown(assets: &dyn Assets, strict: bool, out: &Path) -> Result<(), Error> {
+    let root = find_root()?;
+
+    let project = render_project(&root, strict)?;
+
+    let mut writer = MarkdownWriter::init(out)?;
+    writer.generate_markdown(&project, assets, Some(&root.join(TEMPLATES_DIR)))?;
+
+    Ok(())
+}
+
+fn build_docx(assets: &dyn Assets, strict: bool, out: Option<PathBuf>) -> Result<(), Error> {
+    let root = find_root()?;
+
+    let project = render_project(&root, strict)?;
+
+    let out = out.unwrap_or_else(|| default_output(&root, &project.title, "docx"));
+
+    write_zip(&out, |zip_writer| {
+        zip_writer.generate_docx(&project, assets, Some(&root.join(TEMPLATES_DIR)))
+    })?;
+
+    Ok(())
+}
+
+fn build_odt(assets: &dyn Assets, strict: bool, out: Option<PathBuf>) -> Result<(), Error> {
+    let root = find_root()?;
+
+    let project = render_project(&root, strict)?;
+
+    let out = out.unwrap_or_else(|| default_output(&root, &project.title, "odt"));
+
+    write_zip(&out, |zip_writer| {
+        zip_writer.generate_odt(&project, assets, Some(&root.join(TEMPLATES_DIR)))
+    })?;
+
+    Ok(())
+}
+
+fn build_latex(assets: &dyn Assets, strict: bool, out: &Path) -> Result<(), Error> {
+    let root = find_root()?;
+
+    let project = render_project(&root, strict)?;
+
+    let mut writer = LatexWriter::init(out)?;
+    writer.generate_latex(&project, assets, Some(&root.join(TEMPLATES_DIR)))?;
+
+    Ok(())
+}
+
+fn build_text(
+    assets: &dyn Assets,
+    split: bool,
+    width: Option<usize>,
+    strict: bool,
+    out: Option<PathBuf>,
+) -> Result<(), Error> {
+    let root = find_root()?;
+    let templates = root.join(TEMPLATES_DIR);
+
+    let project = render_project(&root, strict)?;
+
+    if split {
+        let out = out.unwrap_or_else(|| PathBuf::from("out"));
+
+        let mut writer = TextWriter::init(&out, width)?;
+        writer.generate_chapters(&project, assets, Some(&templates))?;
+    } else {
+        let out = out.unwrap_or_else(|| default_output(&root, &project.title, "txt"));
+        let (base, name) = split_output(&out)?;
+
+        let mut writer = TextWriter::init(&base, width)?;
+        writer.generate_book(&project, assets, Some(&templates), &name)?;
+    }
 
     Ok(())
 }
 
-fn wc_chapters(chapters: &Vec<Chapter<Digest>>, mut idx: usize) -> usize {
+fn build_fb2(assets: &dyn Assets, strict: bool, out: Option<PathBuf>) -> Result<(), Error> {
+    let root = find_root()?;
+
+    let project = render_project(&root, strict)?;
+
+    let out = out.unwrap_or_else(|| default_output(&root, &project.title, "fb2"));
+    let (base, name) = split_output(&out)?;
+
+    let mut writer = Fb2Writer::init(&base)?;
+    writer.generate_fb2(&project, assets, Some(&root.join(TEMPLATES_DIR)), &name)?;
+
+    Ok(())
+}
+
+fn wc_chapters(chapters: &[Chapter<Digest>], mut idx: usize) -> usize {
     let mut res = 0;
 
     for c in chapters {
@@ -89,12 +291,11 @@ fn wc_chapters(chapters: &Vec<Chapter<Digest>>, mut idx: usize) -> usize {
     res
 }
 
-fn wc_parts(parts: &Vec<Part<Digest>>) -> usize {
+fn wc_parts(parts: &[Part<Digest>]) -> usize {
     let mut res = 0;
-    let mut idx = 1;
     let mut chap_idx = 1;
 
-    for p in parts {
+    for (idx, p) in (1..).zip(parts.iter()) {
         let part_count = p.content.iter().fold(0, |acc, chap| {
             chap.content.iter().fold(acc, |acc, d| acc + d.words_count)
         });
@@ -124,7 +325,6 @@ fn wc_parts(parts: &Vec<Part<Digest>>) -> usize {
         }
 
         res += part_count;
-        idx += 1;
     }
 
     res
@@ -146,14 +346,13 @@ fn wc() -> Result<(), Error> {
     Ok(())
 }
 
-#[cfg(debug_assertions)]
-fn get_assets() -> Result<PathBuf, Error> {
-    current_dir().or_raise("cannot get current directory")
-}
-
-#[cfg(not(debug_assertions))]
-fn get_assets() -> Result<PathBuf, Error> {
-    Ok(PathBuf::from("/usr/local/share/celtchar"))
+/// The assets compiled into celtchar are used, unless a directory is given
+/// to override them.
+fn get_assets(dir: Option<&str>) -> Box<dyn Assets> {
+    match dir {
+        Some(dir) => Box::new(Directory(PathBuf::from(dir))),
+        None => Box::new(Embedded),
+    }
 }
 
 fn main_with_error() -> Result<(), Error> {
@@ -161,11 +360,104 @@ fn main_with_error() -> Result<(), Error> {
         .version("0.1")
         .author("Thomas Letan")
         .about("A tool to generate novels")
-        .subcommand(SubCommand::with_name("new").about("Create a new celtchar document"))
+        .setting(AppSettings::SubcommandRequiredElseHelp)
+        .arg(
+            Arg::with_name("assets")
+                .value_name("DIRECTORY")
+                .help("Directory of templates and fonts overriding the default ones")
+                .takes_value(true)
+                .env("CELTCHAR_ASSETS")
+                .global(true)
+                .long("assets"),
+        )
+        .subcommand(
+            SubCommand::with_name("new")
+                .about("Create a new celtchar document")
+                .arg(
+                    Arg::with_name("directory")
+                        .value_name("DIRECTORY")
+                        .help("Directory where the project is created")
+                        .required(true)
+                        .index(1),
+                )
+                .arg(
+                    Arg::with_name("title")
+                        .value_name("TITLE")
+                        .help("Title of the book (defaults to the directory name)")
+                        .takes_value(true)
+                        .short("t")
+                        .long("title"),
+                )
+                .arg(
+                    Arg::with_name("author")
+                        .value_name("AUTHOR")
+                        .help("Author of the book")
+                        .takes_value(true)
+                        .short("a")
+                        .long("author"),
+                )
+                .arg(
+                    Arg::with_name("language")
+                        .value_name("LANGUAGE")
+                        .help("Language of the book")
+                        .takes_value(true)
+                        .possible_values(&["en", "fr"])
+                        .default_value("en")
+                        .short("l")
+                        .long("language"),
+                )
+                .arg(
+                    Arg::with_name("layout")
+                        .value_name("LAYOUT")
+                        .help("Organise the book in chapters, or in parts of chapters")
+                        .takes_value(true)
+                        .possible_values(&["chapters", "parts"])
+                        .default_value("chapters")
+                        .long("layout"),
+                ),
+        )
         .subcommand(SubCommand::with_name("wc").about("World count"))
-        .subcommand(SubCommand::with_name("epub").about("Build a epub"))
+        .subcommand(
+            SubCommand::with_name("epub")
+                .about("Build a epub")
+                .arg(
+                    Arg::with_name("epub-version")
+                        .value_name("VERSION")
+                        .help("EPUB version to generate (overrides Book.toml)")
+                        .takes_value(true)
+                        .possible_values(&["2", "3"])
+                        .long("epub-version"),
+                )
+                .arg(
+                    Arg::with_name("subset-fonts")
+                        .help("Only embed the glyphs used by the book")
+                        .takes_value(false)
+                        .long("subset-fonts"),
+                )
+                .arg(
+                    Arg::with_name("strict")
+                        .help("Fail on ill-formed ogam content")
+                        .takes_value(false)
+                        .long("strict"),
+                )
+                .arg(
+                    Arg::with_name("validate")
+                        .help("Check the generated EPUB against the specification")
+                        .takes_value(false)
+                        .long("validate"),
+                )
+                .arg(
+                    Arg::with_name("output")
+                        .value_name("FILE")
+                        .help("Path of the generated EPUB (defaults to <title>.epub)")
+                        .takes_value(true)
+                        .short("o")
+                        .long("output"),
+                ),
+        )
         .subcommand(
             SubCommand::with_name("static")
+                .visible_alias("html")
                 .about("Build a static website")
                 .arg(
                     Arg::with_name("body-only")
@@ -174,6 +466,55 @@ fn main_with_error() -> Result<(), Error> {
                         .short("b")
                         .long("body-only"),
                 )
+                .arg(
+                    Arg::with_name("single")
+                        .help("Build a single self-contained HTML file with the whole book")
+                        .takes_value(false)
+                        .conflicts_with("body-only")
+                        .long("single"),
+                )
+                .arg(
+                    Arg::with_name("strict")
+                        .help("Fail on ill-formed ogam content")
+                        .takes_value(false)
+                        .long("strict"),
+                )
+                .arg(
+                    Arg::with_name("output")
+                        .value_name("PATH")
+                        .help("Output directory where the generated documents are saved, or output file with --single")
+                        .takes_value(true)
+                        .short("o")
+                        .long("output"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("markdown")
+                .about("Build one CommonMark document per chapter, plus an index")
+                .arg(
+                    Arg::with_name("strict")
+                        .help("Fail on ill-formed ogam content")
+                        .takes_value(false)
+                        .long("strict"),
+                )
+                .arg(
+                    Arg::with_name("output")
+                        .value_name("DIRECTORY")
+                        .help("Output directory where the generated documents are saved")
+                        .takes_value(true)
+                        .short("o")
+                        .long("output"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("latex")
+                .about("Build a LaTeX project for a print edition, to be compiled with LuaLaTeX")
+                .arg(
+                    Arg::with_name("strict")
+                        .help("Fail on ill-formed ogam content")
+                        .takes_value(false)
+                        .long("strict"),
+                )
                 .arg(
                     Arg::with_name("output")
                         .value_name("DIRECTORY")
@@ -183,29 +524,269 @@ fn main_with_error() -> Result<(), Error> {
                         .long("output"),
                 ),
         )
+        .subcommand(
+            SubCommand::with_name("docx")
+                .about("Build a Word document of the book, for editors")
+                .arg(
+                    Arg::with_name("strict")
+                        .help("Fail on ill-formed ogam content")
+                        .takes_value(false)
+                        .long("strict"),
+                )
+                .arg(
+                    Arg::with_name("output")
+                        .value_name("FILE")
+                        .help("Path of the generated document (defaults to <title>.docx)")
+                        .takes_value(true)
+                        .short("o")
+                        .long("output"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("fb2")
+                .about("Build a FictionBook of the book, for FB2 readers")
+                .arg(
+                    Arg::with_name("strict")
+                        .help("Fail on ill-formed ogam content")
+                        .takes_value(false)
+                        .long("strict"),
+                )
+                .arg(
+                    Arg::with_name("output")
+                        .value_name("FILE")
+                        .help("Path of the generated document (defaults to <title>.fb2)")
+                        .takes_value(true)
+                        .short("o")
+                        .long("output"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("odt")
+                .about("Build an OpenDocument text of the book, for LibreOffice")
+                .arg(
+                    Arg::with_name("strict")
+                        .help("Fail on ill-formed ogam content")
+                        .takes_value(false)
+                        .long("strict"),
+                )
+                .arg(
+                    Arg::with_name("output")
+                        .value_name("FILE")
+                        .help("Path of the generated document (defaults to <title>.odt)")
+                        .takes_value(true)
+                        .short("o")
+                        .long("output"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("txt")
+                .about("Build a plain text version of the book")
+                .arg(
+                    Arg::with_name("split")
+                        .help("Write one file per chapter, in the output directory")
+                        .takes_value(false)
+                        .long("split"),
+                )
+                .arg(
+                    Arg::with_name("width")
+                        .value_name("COLUMNS")
+                        .help("Wrap lines at this number of characters")
+                        .takes_value(true)
+                        .short("w")
+                        .long("width"),
+                )
+                .arg(
+                    Arg::with_name("strict")
+                        .help("Fail on ill-formed ogam content")
+                        .takes_value(false)
+                        .long("strict"),
+                )
+                .arg(
+                    Arg::with_name("output")
+                        .value_name("PATH")
+                        .help("Output file, or output directory with --split")
+                        .takes_value(true)
+                        .short("o")
+                        .long("output"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("check")
+                .about("Look for issues in a project without building it")
+                .arg(
+                    Arg::with_name("format")
+                        .value_name("FORMAT")
+                        .help("Format of the report")
+                        .takes_value(true)
+                        .possible_values(&["human", "json"])
+                        .default_value("human")
+                        .long("format"),
+                )
+                .arg(
+                    Arg::with_name("strict")
+                        .help("Treat ill-formed ogam content as errors")
+                        .takes_value(false)
+                        .long("strict"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("validate")
+                .about("Check an EPUB against the specification")
+                .arg(
+                    Arg::with_name("file")
+                        .value_name("FILE")
+                        .help("The EPUB to check")
+                        .required(true)
+                        .index(1),
+                ),
+        )
         .subcommand(SubCommand::with_name("deps").about("List dependencies of a celtchar document"))
+        .subcommand(
+            SubCommand::with_name("templates")
+                .about("Manage the templates of a project")
+                .setting(AppSettings::SubcommandRequiredElseHelp)
+                .subcommand(
+                    SubCommand::with_name("export")
+                        .about("Copy the default templates into the project for editing")
+                        .arg(
+                            Arg::with_name("force")
+                                .help("Overwrite the templates already in the project")
+                                .takes_value(false)
+                                .short("f")
+                                .long("force"),
+                        ),
+                ),
+        )
         .get_matches();
 
-    let assets: PathBuf = get_assets()?;
+    let assets = get_assets(matches.value_of("assets"));
 
     match matches.subcommand() {
+        ("new", Some(args)) => {
+            let dir = PathBuf::from(args.value_of("directory").unwrap_or("."));
+            let title = args.value_of("title").map(String::from).unwrap_or(
+                dir.file_name()
+                    .and_then(|x| x.to_str())
+                    .map(String::from)
+                    .unwrap_or_else(|| String::from("Untitled")),
+            );
+            let author = args.value_of("author").unwrap_or("Anonymous");
+            let language = match args.value_of("language") {
+                Some("fr") => Language::Fr,
+                _ => Language::En,
+            };
+            let layout = match args.value_of("layout") {
+                Some("parts") => Layout::Parts,
+                _ => Layout::Chapters,
+            };
+            new_project(&dir, &title, author, language, layout)?
+        }
         ("wc", _) => wc()?,
-        ("epub", _) => build_epub(&assets)?,
+        ("epub", Some(args)) => {
+            let version = match args.value_of("epub-version") {
+                Some("2") => Some(EpubVersion::Epub2),
+                Some("3") => Some(EpubVersion::Epub3),
+                _ => None,
+            };
+            let output = args.value_of("output").map(PathBuf::from);
+            let subset_fonts = args.is_present("subset-fonts");
+            let strict = args.is_present("strict");
+            let out = build_epub(&*assets, version, subset_fonts, strict, output)?;
+
+            if args.is_present("validate") && !validate(&out)? {
+                std::process::exit(1);
+            }
+        }
         ("static", Some(args)) => {
             let body_only = args.is_present("body-only");
+            let strict = args.is_present("strict");
+
+            if args.is_present("single") {
+                let output = args.value_of("output").map(PathBuf::from);
+                build_single_page(&*assets, strict, output)?;
+            } else {
+                let output_dir = PathBuf::from(args.value_of("output").unwrap_or("out"));
+                build_static(&*assets, body_only, strict, &output_dir)?
+            }
+        }
+        ("markdown", Some(args)) => {
+            let output_dir = PathBuf::from(args.value_of("output").unwrap_or("out"));
+            build_markdown(&*assets, args.is_present("strict"), &output_dir)?
+        }
+        ("latex", Some(args)) => {
             let output_dir = PathBuf::from(args.value_of("output").unwrap_or("out"));
-            build_static(&assets, body_only, &output_dir)?
+            build_latex(&*assets, args.is_present("strict"), &output_dir)?
+        }
+        ("docx", Some(args)) => {
+            let output = args.value_of("output").map(PathBuf::from);
+            build_docx(&*assets, args.is_present("strict"), output)?
+        }
+        ("odt", Some(args)) => {
+            let output = args.value_of("output").map(PathBuf::from);
+            build_odt(&*assets, args.is_present("strict"), output)?
+        }
+        ("fb2", Some(args)) => {
+            let output = args.value_of("output").map(PathBuf::from);
+            build_fb2(&*assets, args.is_present("strict"), output)?
+        }
+        ("txt", Some(args)) => {
+            let width = match args.value_of("width") {
+                Some(width) => Some(
+                    width
+                        .parse::<usize>()
+                        .ok()
+                        .filter(|w| *w > 0)
+                        .or_raise("--width expects a positive number of characters")?,
+                ),
+                None => None,
+            };
+            let output = args.value_of("output").map(PathBuf::from);
+            build_text(
+                &*assets,
+                args.is_present("split"),
+                width,
+                args.is_present("strict"),
+                output,
+            )?
+        }
+        ("check", Some(args)) => {
+            let format = match args.value_of("format") {
+                Some("json") => Format::Json,
+                _ => Format::Human,
+            };
+
+            if check(&find_root()?, format, args.is_present("strict"))? > 0 {
+                std::process::exit(1);
+            }
+        }
+        ("validate", Some(args)) => {
+            let file = PathBuf::from(args.value_of("file").unwrap_or_default());
+
+            if !validate(&file)? {
+                std::process::exit(1);
+            }
         }
         ("deps", _) => deps()?,
-        _ => unimplemented!(),
+        ("templates", Some(args)) => match args.subcommand() {
+            ("export", Some(args)) => {
+                export_templates(&*assets, &find_root()?, args.is_present("force"))?
+            }
+            _ => unreachable!(),
+        },
+        _ => unreachable!(),
     }
 
     Ok(())
 }
 
-fn main() -> () {
-    match main_with_error() {
-        Err(Error(msg)) => eprintln!("error: {}", msg),
-        Ok(x) => x,
+fn main() {
+    if let Err(err) = main_with_error() {
+        eprintln!("error: {}", err);
+
+        for cause in err.causes() {
+            eprintln!("  caused by: {}", cause);
+        }
+
+        std::process::exit(1);
     }
 }
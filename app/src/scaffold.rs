@@ -0,0 +1,131 @@
+use std::fs::{create_dir_all, write};
+use std::path::{Path, PathBuf};
+
+use libceltchar::{Assets, Chapter, Content, Error, Language, Part, Project, Raise};
+
+use crate::filesystem::{PROJECT_FILE, TEMPLATES_DIR};
+
+pub enum Layout {
+    Chapters,
+    Parts,
+}
+
+fn starter_chapter(lang: &Language) -> (&'static str, &'static str) {
+    match lang {
+        Language::Fr => (
+            "Premier chapitre",
+            r#"Il était une fois une histoire qui ne demandait qu’à être écrite.
+
+[Par où commencer ?](narrator) *Par le début*, évidemment.
+"#,
+        ),
+        Language::En => (
+            "First chapter",
+            r#"Once upon a time, there was a story waiting to be written.
+
+[Where should I begin?](narrator) *At the beginning*, obviously.
+"#,
+        ),
+    }
+}
+
+fn starter_part(lang: &Language) -> &'static str {
+    match lang {
+        Language::Fr => "Première partie",
+        Language::En => "First part",
+    }
+}
+
+pub fn new_project(
+    dir: &Path,
+    title: &str,
+    author: &str,
+    language: Language,
+    layout: Layout,
+) -> Result<(), Error> {
+    let manifest = dir.join(PROJECT_FILE);
+
+    if manifest.exists() {
+        return Err(Error::new(&format!("{:?} already exists", manifest)));
+    }
+
+    let (chapter_title, chapter_content) = starter_chapter(&language);
+
+    let (chapter_path, content) = match layout {
+        Layout::Chapters => {
+            let path = PathBuf::from("chapters/01.ogam");
+            let chapters = vec![Chapter {
+                title: Some(String::from(chapter_title)),
+                content: vec![path.clone()],
+            }];
+
+            (path, Content::WithChapters(chapters))
+        }
+        Layout::Parts => {
+            let path = PathBuf::from("part1/01.ogam");
+            let parts = vec![Part {
+                title: Some(String::from(starter_part(&language))),
+                content: vec![Chapter {
+                    title: Some(String::from(chapter_title)),
+                    content: vec![path.clone()],
+                }],
+            }];
+
+            (path, Content::WithParts(parts))
+        }
+    };
+
+    let project: Project<PathBuf, PathBuf> = Project {
+        author: String::from(author),
+        title: String::from(title),
+        description: None,
+        cover: None,
+        numbering: Some(true),
+        language,
+        epub_version: None,
+        metadata: None,
+        fonts: None,
+        subset_fonts: None,
+        characters: None,
+        epub_character_styles: None,
+        content,
+    };
+
+    let input = toml::to_string(&project).or_raise("Could not serialize Book.toml")?;
+
+    let chapter_path = dir.join(chapter_path);
+
+    if let Some(parent) = chapter_path.parent() {
+        create_dir_all(parent).map_err(|e| Error::io("create directory", parent, e))?;
+    }
+
+    write(&chapter_path, chapter_content).map_err(|e| Error::io("write", &chapter_path, e))?;
+    write(&manifest, input).map_err(|e| Error::io("write", &manifest, e))?;
+
+    Ok(())
+}
+
+/// Copy the default templates into the `templates/` directory of a
+/// project, where they can be edited.  Templates already present in the
+/// project are only overwritten when `force` is set.
+pub fn export_templates(assets: &dyn Assets, root: &Path, force: bool) -> Result<(), Error> {
+    let dir = root.join(TEMPLATES_DIR);
+
+    for (name, content) in assets.templates()? {
+        let dst = dir.join(&name);
+
+        if dst.exists() && !force {
+            println!("skip {:?} (already exists)", dst);
+            continue;
+        }
+
+        if let Some(parent) = dst.parent() {
+            create_dir_all(parent).map_err(|e| Error::io("create directory", parent, e))?;
+        }
+
+        write(&dst, content).map_err(|e| Error::io("write", &dst, e))?;
+        println!("{:?}", dst);
+    }
+
+    Ok(())
+}
@@ -0,0 +1,179 @@
+extern crate serde_json;
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use serde_json::Value;
+
+/// Create a project named `name` in a temporary directory, with the given
+/// `Book.toml` and documents.
+fn project(name: &str, manifest: &str, documents: &[(&str, &str)]) -> PathBuf {
+    let root = std::env::temp_dir().join(format!("celtchar-check-{}", name));
+
+    let _ = fs::remove_dir_all(&root);
+    fs::create_dir_all(&root).unwrap();
+    fs::write(root.join("Book.toml"), manifest).unwrap();
+
+    for (file, content) in documents {
+        fs::write(root.join(file), content).unwrap();
+    }
+
+    root
+}
+
+/// Run `celtchar check --format json` in `root`, and return its exit code
+/// and its report.
+fn check(root: &Path, strict: bool) -> (i32, Value) {
+    let mut cmd = Command::new(env!("CARGO_BIN_EXE_celtchar"));
+    cmd.current_dir(root).args(["check", "--format", "json"]);
+
+    if strict {
+        cmd.arg("--strict");
+    }
+
+    let output = cmd.output().unwrap();
+    let report = serde_json::from_slice(&output.stdout).unwrap();
+
+    (output.status.code().unwrap(), report)
+}
+
+fn kinds(report: &Value) -> Vec<&str> {
+    report["issues"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|i| i["kind"].as_str().unwrap())
+        .collect()
+}
+
+#[test]
+fn reports_content_issues_along_with_duplicates() {
+    let root = project(
+        "duplicates",
+        r#"author = "A"
+title = "T"
+language = "En"
+chapters = [ { title = "One", content = ["a.ogam", "./a.ogam"] } ]
+"#,
+        &[("a.ogam", "Once upon&nbsp;a time.\n")],
+    );
+
+    let (code, report) = check(&root, false);
+
+    assert_eq!(code, 1);
+    assert_eq!(
+        kinds(&report),
+        vec![
+            "no-cover",
+            "duplicate-file",
+            "unescaped-html",
+            "unescaped-html"
+        ]
+    );
+}
+
+#[test]
+fn warns_about_empty_chapters() {
+    let root = project(
+        "empty",
+        r#"author = "A"
+title = "T"
+language = "En"
+chapters = [ { title = "One", content = [] }, { title = "Two", content = ["b.ogam"] } ]
+"#,
+        &[("b.ogam", "\n\n")],
+    );
+
+    let (code, report) = check(&root, false);
+
+    assert_eq!(code, 0);
+    assert_eq!(
+        kinds(&report),
+        vec!["no-cover", "empty-chapter", "empty-chapter"]
+    );
+    assert_eq!(report["errors"], 0);
+    assert_eq!(report["warnings"], 3);
+}
+
+#[test]
+fn reports_content_issues_along_with_a_missing_cover() {
+    let root = project(
+        "cover",
+        r#"author = "A"
+title = "T"
+language = "En"
+cover = "cover.png"
+chapters = [ { title = "One", content = ["a.ogam"] } ]
+"#,
+        &[("a.ogam", "Once upon&nbsp;a time.\n")],
+    );
+
+    let (code, report) = check(&root, false);
+
+    assert_eq!(code, 1);
+    assert_eq!(kinds(&report), vec!["missing-cover", "unescaped-html"]);
+}
+
+#[test]
+fn describes_issues_in_json() {
+    let root = project(
+        "json",
+        r#"author = "A"
+title = "T"
+language = "En"
+chapters = [ { title = "One", content = ["a.ogam", "c.ogam"] } ]
+"#,
+        &[("a.ogam", "First line.\nSecond&nbsp;line.\n")],
+    );
+
+    let (_, report) = check(&root, false);
+
+    assert_eq!(report["errors"], 1);
+    assert_eq!(report["warnings"], 1);
+    assert_eq!(
+        report["issues"][1],
+        serde_json::json!({
+            "severity": "error",
+            "kind": "missing-file",
+            "file": "c.ogam",
+            "line": null,
+            "column": null,
+            "message": "document of chapter 1 (One) not found",
+            "excerpt": null,
+        })
+    );
+}
+
+#[test]
+fn fails_on_warnings_in_strict_mode() {
+    let root = project(
+        "strict",
+        r#"author = "A"
+title = "T"
+language = "En"
+chapters = [ { title = "One", content = ["a.ogam"] } ]
+"#,
+        &[("a.ogam", "Once upon&nbsp;a time.\n")],
+    );
+
+    let (code, report) = check(&root, false);
+    assert_eq!(code, 0);
+    assert_eq!(report["errors"], 0);
+
+    let (code, report) = check(&root, true);
+    assert_eq!(code, 1);
+    assert_eq!(report["errors"], 1);
+    assert_eq!(
+        report["issues"][1],
+        serde_json::json!({
+            "severity": "error",
+            "kind": "unescaped-html",
+            "file": "a.ogam",
+            "line": 1,
+            "column": 10,
+            "message": "HTML entity rendered literally",
+            "excerpt": "Once upon&nbsp;a time.",
+        })
+    );
+}
@@ -1,10 +1,165 @@
 use crate::error::{Error, Raise};
-use std::path::PathBuf;
+use std::fs::{read, read_dir};
+use std::path::{Path, PathBuf};
+use tera::Tera;
 
-pub fn template_dir(assets : &PathBuf) -> Result<String, Error> {
-    let mut res = assets.clone();
+/// Provide the default templates and fonts used to generate a book.
+pub trait Assets {
+    /// The templates, as pairs of a name (*e.g.*, `epub/main.css`) and a
+    /// content.
+    fn templates(&self) -> Result<Vec<(String, String)>, Error>;
+
+    fn font(&self, name : &Path) -> Result<Vec<u8>, Error>;
+}
+
+macro_rules! embed {
+    ($root:expr, $($name:expr),* $(,)*) => {
+        &[$(($name, include_bytes!(concat!("../", $root, "/", $name)))),*]
+    };
+}
+
+const TEMPLATES : &[(&str, &[u8])] = embed!(
+    "templates",
+    "docx/chapter.xml",
+    "docx/content_types.xml",
+    "docx/core.xml",
+    "docx/document.xml",
+    "docx/document.xml.rels",
+    "docx/rels.xml",
+    "docx/styles.xml",
+    "epub/chapter.xhtml",
+    "epub/container.xml",
+    "epub/content.opf",
+    "epub/cover.xhtml",
+    "epub/main.css",
+    "epub/part.xhtml",
+    "epub/toc.ncx",
+    "epub3/chapter.xhtml",
+    "epub3/content.opf",
+    "epub3/cover.xhtml",
+    "epub3/nav.xhtml",
+    "epub3/part.xhtml",
+    "fb2/book.fb2",
+    "fb2/chapter.fb2",
+    "latex/book.tex",
+    "latex/chapter.tex",
+    "latex/latexmkrc",
+    "markdown/chapter.md",
+    "markdown/index.md",
+    "odt/chapter.xml",
+    "odt/content.xml",
+    "odt/manifest.xml",
+    "odt/meta.xml",
+    "odt/styles.xml",
+    "static/book.html",
+    "static/book_chapter.html",
+    "static/chapter.html",
+    "static/index.html",
+    "static/part.html",
+    "static/social.html",
+    "static/style.css",
+    "text/book.txt",
+    "text/chapter.txt",
+);
+
+const FONTS : &[(&str, &[u8])] = embed!(
+    "fonts",
+    "et-book-bold-line-figures.ttf",
+    "et-book-display-italic-old-style-figures.ttf",
+    "et-book-roman-line-figures.ttf",
+);
+
+/// The assets compiled into celtchar.
+pub struct Embedded;
+
+impl Assets for Embedded {
+    fn templates(&self) -> Result<Vec<(String, String)>, Error> {
+        TEMPLATES
+            .iter()
+            .map(|(name, content)| {
+                String::from_utf8(content.to_vec())
+                    .map(|content| (String::from(*name), content))
+                    .or_raise(&format!("Template {} is not valid UTF-8", name))
+            })
+            .collect()
+    }
+
+    fn font(&self, name : &Path) -> Result<Vec<u8>, Error> {
+        FONTS
+            .iter()
+            .find(|(font, _)| Path::new(font) == name)
+            .map(|(_, content)| content.to_vec())
+            .or_raise(&format!("Unknown font {:?}", name))
+    }
+}
+
+/// Assets read from a directory with a `templates/` and a `fonts/`
+/// subdirectories.  The files missing from the directory are taken from the
+/// embedded assets.
+pub struct Directory(pub PathBuf);
+
+fn read_templates(
+    dir : &Path,
+    prefix : &str,
+    acc : &mut Vec<(String, String)>,
+) -> Result<(), Error> {
+    let entries = read_dir(dir).map_err(|e| Error::io("read directory", dir, e))?;
+
+    for entry in entries {
+        let entry = entry.map_err(|e| Error::io("read directory", dir, e))?;
+        let path = entry.path();
+        let name = entry
+            .file_name()
+            .to_str()
+            .map(|x| format!("{}{}", prefix, x))
+            .or_raise(&format!("{:?} is not a valid template name", path))?;
+
+        if path.is_dir() {
+            read_templates(&path, &format!("{}/", name), acc)?;
+        } else {
+            let content = read(&path).map_err(|e| Error::io("read", &path, e))?;
+            let content = String::from_utf8(content)
+                .or_raise(&format!("Template {:?} is not valid UTF-8", path))?;
+
+            acc.push((name, content));
+        }
+    }
+
+    Ok(())
+}
+
+impl Assets for Directory {
+    fn templates(&self) -> Result<Vec<(String, String)>, Error> {
+        let mut res = vec![];
+        let dir = self.0.join("templates");
+
+        if dir.is_dir() {
+            read_templates(&dir, "", &mut res)?;
+        }
+
+        for (name, content) in Embedded.templates()? {
+            if !res.iter().any(|(x, _)| x == &name) {
+                res.push((name, content));
+            }
+        }
+
+        Ok(res)
+    }
+
+    fn font(&self, name : &Path) -> Result<Vec<u8>, Error> {
+        let path = self.0.join("fonts").join(name);
+
+        if path.is_file() {
+            read(&path).map_err(|e| Error::io("read", path, e))
+        } else {
+            Embedded.font(name)
+        }
+    }
+}
+
+fn template_glob(dir : &Path) -> Result<String, Error> {
+    let mut res = dir.to_path_buf();
 
-    res.push("templates");
     res.push("**");
     res.push("*");
 
@@ -13,10 +168,65 @@ pub fn template_dir(assets : &PathBuf) -> Result<String, Error> {
         .or_raise("Compute template dir")
 }
 
-pub fn fonts_dir(assets : &PathBuf) -> Result<PathBuf, Error> {
-    let mut res = assets.clone();
+/// Load the templates of the assets.  Templates found in `overrides`, if
+/// any, take precedence over the ones with the same name in the assets.
+pub fn load_templates(assets : &dyn Assets, overrides : Option<&Path>) -> Result<Tera, Error> {
+    let mut defaults = Tera::default();
+
+    defaults
+        .add_raw_templates(assets.templates()?)
+        .map_err(|e| Error::template(None, e))?;
+
+    let mut tera = match overrides {
+        Some(dir) if dir.is_dir() => Tera::parse(template_glob(dir)?.as_str())
+            .map_err(|e| Error::template(None, e))?,
+        _ => Tera::default(),
+    };
+
+    tera.extend(&defaults)
+        .map_err(|e| Error::template(None, e))?;
+
+    Ok(tera)
+}
+
+/// Read a file of the templates, giving precedence to `overrides`.
+pub fn template_file(
+    assets : &dyn Assets,
+    overrides : Option<&Path>,
+    name : &str,
+) -> Result<Vec<u8>, Error> {
+    match overrides.map(|dir| dir.join(name)) {
+        Some(ref file) if file.is_file() => {
+            read(file).map_err(|e| Error::io("read", file, e))
+        }
+        _ => assets
+            .templates()?
+            .into_iter()
+            .find(|(x, _)| x == name)
+            .map(|(_, content)| content.into_bytes())
+            .or_raise(&format!("Unknown template {}", name)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{read_templates, TEMPLATES};
+    use std::path::Path;
+
+    #[test]
+    fn embeds_every_template() {
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("templates");
+        let mut files = vec![];
+        read_templates(&dir, "", &mut files).unwrap();
 
-    res.push("fonts");
+        let mut names : Vec<_> = files.into_iter().map(|(name, _)| name).collect();
+        names.sort();
+        let mut embedded : Vec<_> = TEMPLATES
+            .iter()
+            .map(|(name, _)| String::from(*name))
+            .collect();
+        embedded.sort();
 
-    Ok(res)
+        assert_eq!(names, embedded);
+    }
 }
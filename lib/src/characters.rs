@@ -0,0 +1,184 @@
+use serde_derive::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+use crate::error::Error;
+
+/// A character of the book, known to ogam by an author id, as in
+/// `[Hello there.](anna)`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Character {
+    /// The name displayed to readers
+    pub name: String,
+    /// A CSS colour for the replies of the character
+    pub colour: Option<String>,
+    /// CSS declarations applied to the replies of the character, *e.g.*,
+    /// `font-style: italic`
+    pub style: Option<String>,
+}
+
+/// Whether `value` can be used as the value of a CSS declaration without
+/// leaking into the rules around it.
+fn is_css_value(value: &str) -> bool {
+    !value.trim().is_empty()
+        && !value.contains(['{', '}', '<', '>', ';'])
+        && !value.contains("/*")
+        && !value.contains("*/")
+}
+
+/// Whether `item` is a plain `property: value` declaration.
+fn is_css_declaration(item: &str) -> bool {
+    match item.split_once(':') {
+        Some((property, value)) => {
+            let property = property.trim();
+
+            !property.is_empty()
+                && property
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '-')
+                && is_css_value(value)
+        }
+        None => false,
+    }
+}
+
+fn invalid_css(id: &str, what: &str, value: &str) -> Error {
+    Error::new(&format!(
+        "the {} of character {} is not a valid CSS value: {}",
+        what, id, value
+    ))
+}
+
+/// Quote `value` as a CSS string.  `<` is escaped too, for the rules to be
+/// embedded in a `<style>` element.
+fn css_string(value: &str) -> String {
+    let mut res = String::with_capacity(value.len() + 2);
+
+    res.push('"');
+    for c in value.chars() {
+        match c {
+            '"' | '\\' => {
+                res.push('\\');
+                res.push(c);
+            }
+            '<' => res.push_str("\\3c "),
+            c if c.is_control() => res.push_str(&format!("\\{:x} ", c as u32)),
+            c => res.push(c),
+        }
+    }
+    res.push('"');
+
+    res
+}
+
+/// The CSS rules styling the replies of the characters, which the HTML
+/// output tags with a `data-character` attribute.
+pub fn stylesheet(characters: &BTreeMap<String, Character>) -> Result<String, Error> {
+    let mut res = String::new();
+
+    for (id, character) in characters {
+        let mut declarations = vec![];
+
+        if let Some(ref colour) = character.colour {
+            let value = colour.trim().trim_end_matches(';');
+
+            if !is_css_value(value) {
+                return Err(invalid_css(id, "colour", colour));
+            }
+
+            declarations.push(format!("color: {};", value));
+        }
+
+        if let Some(ref style) = character.style {
+            let value = style.trim().trim_end_matches(';');
+
+            if !value.split(';').all(is_css_declaration) {
+                return Err(invalid_css(id, "style", style));
+            }
+
+            declarations.push(format!("{};", value));
+        }
+
+        if !declarations.is_empty() {
+            res.push_str(&format!(
+                "\n/* {} */\n.reply[data-character={}] {{\n    {}\n}}\n",
+                character.name.replace("*/", "* /"),
+                css_string(id),
+                declarations.join("\n    ")
+            ));
+        }
+    }
+
+    Ok(res)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{css_string, is_css_declaration, is_css_value, stylesheet, Character};
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn accepts_plain_values() {
+        assert!(is_css_value("red"));
+        assert!(is_css_value(" rgb(200, 0, 0) "));
+        assert!(is_css_value(r#""ET Book", serif"#));
+
+        assert!(!is_css_value(""));
+        assert!(!is_css_value("red}"));
+        assert!(!is_css_value("red; font-size: 300%"));
+        assert!(!is_css_value("red /* comment"));
+        assert!(!is_css_value("red */"));
+        assert!(!is_css_value("</style>"));
+    }
+
+    #[test]
+    fn accepts_plain_declarations() {
+        assert!(is_css_declaration("font-style: italic"));
+        assert!(is_css_declaration(" -webkit-text-stroke : 1px "));
+
+        assert!(!is_css_declaration("italic"));
+        assert!(!is_css_declaration(": italic"));
+        assert!(!is_css_declaration("font style: italic"));
+        assert!(!is_css_declaration("font-style:"));
+        assert!(!is_css_declaration("font-style: italic /*"));
+    }
+
+    #[test]
+    fn quotes_ids() {
+        assert_eq!(css_string("old-man"), r#""old-man""#);
+        assert_eq!(css_string(r#"a"b\c"#), r#""a\"b\\c""#);
+        assert_eq!(css_string("a<b\n"), r#""a\3c b\a ""#);
+    }
+
+    fn character(colour: Option<&str>, style: Option<&str>) -> Character {
+        Character {
+            name: String::from("Anna"),
+            colour: colour.map(String::from),
+            style: style.map(String::from),
+        }
+    }
+
+    #[test]
+    fn styles_replies() {
+        let mut characters = BTreeMap::new();
+        characters.insert(
+            String::from("anna"),
+            character(Some("red;"), Some("font-style: italic; font-weight: bold;")),
+        );
+
+        assert_eq!(
+            stylesheet(&characters).unwrap(),
+            "\n/* Anna */\n.reply[data-character=\"anna\"] {\n    color: red;\n    font-style: italic; font-weight: bold;\n}\n"
+        );
+    }
+
+    #[test]
+    fn rejects_comments_in_styles() {
+        let mut characters = BTreeMap::new();
+        characters.insert(
+            String::from("anna"),
+            character(None, Some("font-style: italic; /* color: red")),
+        );
+
+        assert!(stylesheet(&characters).is_err());
+    }
+}
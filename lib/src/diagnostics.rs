@@ -0,0 +1,227 @@
+use std::fmt;
+
+use ogam::ast::{Component, Document, Paragraph, Section};
+use serde_derive::Serialize;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum DiagnosticKind {
+    /// ogam could not make sense of the document at all
+    Syntax,
+    /// A block ogam could not parse, rendered as is
+    IllFormedBlock,
+    /// A span of a paragraph ogam could not parse, rendered as is
+    IllFormedInline,
+    /// An HTML entity, such as `&nbsp;`, which is escaped like any other
+    /// text and thus shows up literally in the generated documents
+    UnescapedHtml,
+}
+
+impl DiagnosticKind {
+    pub fn describe(self) -> &'static str {
+        match self {
+            DiagnosticKind::Syntax => "syntax error",
+            DiagnosticKind::IllFormedBlock => "ill-formed block",
+            DiagnosticKind::IllFormedInline => "ill-formed span",
+            DiagnosticKind::UnescapedHtml => "HTML entity rendered literally",
+        }
+    }
+}
+
+/// An issue found in an ogam document, located by its line and column
+/// (both starting at 1).
+#[derive(Debug, Clone, Serialize)]
+pub struct Diagnostic {
+    pub document: String,
+    pub kind: DiagnosticKind,
+    pub line: usize,
+    pub column: usize,
+    /// The line of the document where the issue lies
+    pub excerpt: String,
+    /// The number of characters of the excerpt the issue spans
+    pub length: usize,
+}
+
+impl Diagnostic {
+    fn at(document: &str, kind: DiagnosticKind, input: &str, offset: usize, len: usize) -> Self {
+        let before = &input[..offset];
+        let start = before.rfind('\n').map_or(0, |x| x + 1);
+        let end = input[offset..]
+            .find('\n')
+            .map_or(input.len(), |x| offset + x);
+        let column = before[start..].chars().count() + 1;
+        let excerpt = &input[start..end];
+
+        Diagnostic {
+            document: String::from(document),
+            kind,
+            line: before.matches('\n').count() + 1,
+            column,
+            excerpt: String::from(excerpt),
+            length: input[offset..(offset + len).min(end)]
+                .chars()
+                .count()
+                .max(1),
+        }
+    }
+
+    /// Describe why ogam failed to parse `input`.
+    pub fn syntax(document: &str, input: &str, err: &ogam::Error) -> Self {
+        let offset = match err {
+            ogam::Error::IncompleteParsing(_, rest) => input.len() - rest.len(),
+            ogam::Error::ParsingError => 0,
+        };
+
+        let len = input[offset..].chars().next().map_or(0, char::len_utf8);
+
+        Diagnostic::at(document, DiagnosticKind::Syntax, input, offset, len)
+    }
+
+    /// List the ill-formed blocks and spans of a document parsed from
+    /// `input`, in order of appearance.
+    pub fn ill_formed(document: &str, input: &str, doc: &Document) -> Vec<Self> {
+        // ogam keeps the ill-formed fragments as slices of its input, which
+        // tells us where they are.
+        let locate = |fragment: &str| {
+            let offset = (fragment.as_ptr() as usize).wrapping_sub(input.as_ptr() as usize);
+
+            if offset <= input.len() {
+                Some(offset)
+            } else {
+                input.find(fragment)
+            }
+        };
+
+        let mut res = vec![];
+
+        let paragraphs = |paragraphs: &[Paragraph], res: &mut Vec<Self>| {
+            for Paragraph(components) in paragraphs {
+                for component in components {
+                    if let Component::IllFormed(fragment) = component {
+                        if let Some(offset) = locate(fragment) {
+                            res.push(Diagnostic::at(
+                                document,
+                                DiagnosticKind::IllFormedInline,
+                                input,
+                                offset,
+                                fragment.len(),
+                            ));
+                        }
+                    }
+                }
+            }
+        };
+
+        for section in &doc.0 {
+            match section {
+                Section::Story(paras) | Section::Aside(_, paras) => paragraphs(paras, &mut res),
+                Section::IllFormed(lines) => {
+                    if let Some(offset) = lines.first().and_then(|l| locate(l)) {
+                        res.push(Diagnostic::at(
+                            document,
+                            DiagnosticKind::IllFormedBlock,
+                            input,
+                            offset,
+                            lines[0].len(),
+                        ));
+                    }
+                }
+            }
+        }
+
+        res
+    }
+
+    /// List the HTML entities of `input`, *e.g.*, `&nbsp;` or `&#8230;`.
+    /// Manuscripts are escaped, so these are not interpreted, and the
+    /// characters they stand for should be typed instead.
+    pub fn unescaped_html(document: &str, input: &str) -> Vec<Self> {
+        let entity_len = |rest: &str| {
+            let name = rest
+                .find(|c: char| !(c.is_ascii_alphanumeric() || c == '#'))
+                .map_or(rest, |x| &rest[..x]);
+
+            Some(name.len())
+                .filter(|len| *len > 0 && rest[*len..].starts_with(';'))
+                .map(|len| len + 2)
+        };
+
+        input
+            .match_indices('&')
+            .filter_map(|(offset, _)| {
+                entity_len(&input[offset + 1..]).map(|len| {
+                    Diagnostic::at(document, DiagnosticKind::UnescapedHtml, input, offset, len)
+                })
+            })
+            .collect()
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let line = self.line.to_string();
+        let margin = " ".repeat(line.len());
+
+        writeln!(
+            f,
+            "{}:{}:{}: {}",
+            self.document,
+            self.line,
+            self.column,
+            self.kind.describe()
+        )?;
+        writeln!(f, "{} |", margin)?;
+        writeln!(f, "{} | {}", line, self.excerpt)?;
+        write!(
+            f,
+            "{} | {}{}",
+            margin,
+            " ".repeat(self.column - 1),
+            "^".repeat(self.length)
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Diagnostic, DiagnosticKind};
+    use ogam::ast::Document;
+
+    #[test]
+    fn locates_lines_and_columns() {
+        let input = "First line.\nSecond &amp; last line.";
+        let diagnostics = Diagnostic::unescaped_html("ch.ogam", input);
+
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].kind, DiagnosticKind::UnescapedHtml);
+        assert_eq!(diagnostics[0].line, 2);
+        assert_eq!(diagnostics[0].column, 8);
+        assert_eq!(diagnostics[0].excerpt, "Second &amp; last line.");
+        assert_eq!(diagnostics[0].length, 5);
+    }
+
+    #[test]
+    fn counts_columns_in_characters() {
+        let input = "Déjà vu.\n« Où ça ? » &nbsp;";
+        let diagnostics = Diagnostic::unescaped_html("ch.ogam", input);
+
+        assert_eq!(diagnostics[0].line, 2);
+        assert_eq!(diagnostics[0].column, 13);
+        assert_eq!(diagnostics[0].excerpt, "« Où ça ? » &nbsp;");
+        assert_eq!(
+            diagnostics[0].to_string(),
+            "ch.ogam:2:13: HTML entity rendered literally\n  |\n2 | « Où ça ? » &nbsp;\n  |             ^^^^^^"
+        );
+    }
+
+    #[test]
+    fn points_at_a_multibyte_character() {
+        let input = "Il dit :\n« Bonjour";
+        let err = ogam::Error::IncompleteParsing(Document(vec![]), &input[9..]);
+        let diagnostic = Diagnostic::syntax("ch.ogam", input, &err);
+
+        assert_eq!(diagnostic.line, 2);
+        assert_eq!(diagnostic.column, 1);
+        assert_eq!(diagnostic.length, 1);
+    }
+}
@@ -0,0 +1,245 @@
+use ogam::generator::Output;
+use ogam::typography::Space;
+
+use crate::render::{escape_xml, StoryBreaks};
+
+/// The paragraphs of a WordprocessingML document body, using the styles
+/// `Aside`, `SceneBreak`, `Dialogue` and `Thought`.
+pub struct Docx {
+    out: String,
+    /// The escaped text of the run being rendered
+    run: String,
+    italic: bool,
+    bold: bool,
+    /// The character style of the run being rendered
+    style: Option<&'static str>,
+    /// The style of the paragraphs being rendered
+    paragraph: Option<&'static str>,
+    /// Whether we are rendering the lines of an ill-formed block
+    illformed_block: bool,
+    breaks: StoryBreaks,
+}
+
+impl Docx {
+    pub fn as_str(&self) -> &str {
+        &self.out
+    }
+
+    pub fn into_string(self) -> String {
+        self.out
+    }
+
+    /// Write the run being rendered, if any, with its properties.
+    fn flush(&mut self) {
+        if self.run.is_empty() {
+            return;
+        }
+
+        self.out.push_str("<w:r>");
+
+        if self.italic || self.bold || self.style.is_some() {
+            self.out.push_str("<w:rPr>");
+            if let Some(style) = self.style {
+                self.out
+                    .push_str(&format!("<w:rStyle w:val=\"{}\"/>", style));
+            }
+            if self.bold {
+                self.out.push_str("<w:b/>");
+            }
+            if self.italic {
+                self.out.push_str("<w:i/>");
+            }
+            self.out.push_str("</w:rPr>");
+        }
+
+        self.out.push_str("<w:t xml:space=\"preserve\">");
+        self.out.push_str(&self.run);
+        self.out.push_str("</w:t></w:r>");
+        self.run.clear();
+    }
+
+    fn open_paragraph(&mut self) {
+        self.out.push_str("<w:p>");
+        if let Some(style) = self.paragraph {
+            self.out
+                .push_str(&format!("<w:pPr><w:pStyle w:val=\"{}\"/></w:pPr>", style));
+        }
+    }
+
+    fn close_paragraph(&mut self) {
+        self.flush();
+        self.out.push_str("</w:p>");
+    }
+
+    /// Render `format` in a character style.
+    fn styled<F>(&mut self, style: &'static str, format: F)
+    where
+        F: FnOnce(&mut Docx),
+    {
+        self.flush();
+        let previous = self.style.replace(style);
+        format(self);
+        self.flush();
+        self.style = previous;
+    }
+}
+
+impl Output for Docx {
+    fn empty(input_size: usize) -> Docx {
+        Docx {
+            out: String::with_capacity(3 * input_size),
+            run: String::new(),
+            italic: false,
+            bold: false,
+            style: None,
+            paragraph: None,
+            illformed_block: false,
+            breaks: StoryBreaks::default(),
+        }
+    }
+
+    fn render_space(&mut self, space: Space) {
+        self.run.push_str(match space {
+            Space::Normal => " ",
+            Space::Nbsp => "\u{a0}",
+            Space::None => "",
+        })
+    }
+
+    fn render_word(&mut self, word: &str) {
+        escape_xml(word, &mut self.run)
+    }
+
+    fn render_mark(&mut self, mark: &str) {
+        escape_xml(mark, &mut self.run)
+    }
+
+    fn render_illformed(&mut self, err: &str) {
+        // `w:br` separates the lines of an ill-formed block
+        if self.illformed_block && !self.run.is_empty() {
+            self.flush();
+            self.out.push_str("<w:r><w:br/></w:r>");
+        }
+        escape_xml(err, &mut self.run)
+    }
+
+    fn emph_template<F>(&mut self, format: F)
+    where
+        F: FnOnce(&mut Docx),
+    {
+        self.flush();
+        self.italic = !self.italic;
+        format(self);
+        self.flush();
+        self.italic = !self.italic;
+    }
+
+    fn strong_emph_template<F>(&mut self, format: F)
+    where
+        F: FnOnce(&mut Docx),
+    {
+        self.flush();
+        let previous = self.bold;
+        self.bold = true;
+        format(self);
+        self.flush();
+        self.bold = previous;
+    }
+
+    fn reply_template<F>(&mut self, reply: F, _author: &Option<&str>)
+    where
+        F: FnOnce(&mut Docx),
+    {
+        reply(self);
+    }
+
+    fn thought_template<F>(&mut self, reply: F, _author: &Option<&str>)
+    where
+        F: FnOnce(&mut Docx),
+    {
+        self.styled("Thought", reply);
+    }
+
+    fn dialogue_template<F>(&mut self, reply: F, _author: &Option<&str>)
+    where
+        F: FnOnce(&mut Docx),
+    {
+        self.styled("Dialogue", reply);
+    }
+
+    fn between_dialogue(&mut self) {
+        self.close_paragraph();
+        self.open_paragraph();
+    }
+
+    fn illformed_inline_template<F>(&mut self, err: F)
+    where
+        F: FnOnce(&mut Docx),
+    {
+        err(self);
+    }
+
+    fn paragraph_template<F>(&mut self, para: F)
+    where
+        F: FnOnce(&mut Docx),
+    {
+        self.open_paragraph();
+        para(self);
+        self.close_paragraph();
+    }
+
+    fn illformed_block_template<F>(&mut self, err: F)
+    where
+        F: FnOnce(&mut Docx),
+    {
+        self.open_paragraph();
+        self.illformed_block = true;
+        err(self);
+        self.illformed_block = false;
+        self.close_paragraph();
+        self.breaks.other();
+    }
+
+    fn story_template<F>(&mut self, story: F)
+    where
+        F: FnOnce(&mut Docx),
+    {
+        if self.breaks.story() {
+            self.out.push_str(
+                "<w:p><w:pPr><w:pStyle w:val=\"SceneBreak\"/></w:pPr>\
+                 <w:r><w:t>* * *</w:t></w:r></w:p>",
+            );
+        }
+        story(self);
+    }
+
+    fn aside_template<F>(&mut self, _cls: &Option<&str>, aside: F)
+    where
+        F: FnOnce(&mut Docx),
+    {
+        let previous = self.paragraph.replace("Aside");
+        aside(self);
+        self.paragraph = previous;
+        self.breaks.other();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Docx;
+    use ogam::typography::ENGLISH;
+
+    #[test]
+    fn docx_renders_runs() {
+        let res = ogam::compile::<Docx, _>("Fish & *chips* [Hi +there+.]", &ENGLISH)
+            .unwrap()
+            .into_string();
+
+        assert!(res.starts_with("<w:p><w:r><w:t xml:space=\"preserve\">Fish &amp;</w:t></w:r>"));
+        assert!(res.contains("<w:rPr><w:i/></w:rPr><w:t xml:space=\"preserve\"> chips</w:t>"));
+        assert!(res.contains(
+            "<w:rPr><w:rStyle w:val=\"Dialogue\"/><w:b/></w:rPr><w:t xml:space=\"preserve\"> there</w:t>"
+        ));
+        assert!(res.ends_with("</w:p>"));
+    }
+}
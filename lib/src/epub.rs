@@ -1,23 +1,182 @@
-use std::io::{Read, Write};
-use std::path::PathBuf;
+use std::io::{Read, Seek, Write};
+use std::path::{Path, PathBuf};
 
-use serde_json::json;
+use serde_json::{json, Value};
 
 use tera::{Context, Tera};
 
 use crate::error::{Error, Raise};
-use crate::project::{Chapter, Cover, Language, Project};
+use crate::project::{Chapter, Content, Cover, EpubVersion, Language, Part, Project};
 
-use std::collections::HashSet;
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+use std::env;
 use std::fs::File;
+use std::time::{SystemTime, UNIX_EPOCH};
+use zip::result::ZipError;
 use zip::write::FileOptions;
 use zip::ZipWriter;
+use zip::{CompressionMethod, DateTime};
 
-use crate::assets::{fonts_dir, template_dir};
+use crate::assets::{load_templates, Assets};
+use crate::characters::stylesheet;
+use crate::fonts::FontFamily;
 use crate::render::Html;
+use crate::subset::{subset_font, used_chars};
+use crate::utils::roman_filter;
 use crate::writer::BookWriter;
 
-const EPUB_MIMETYPE: &'static str = "application/epub+zip";
+const EPUB_MIMETYPE: &str = "application/epub+zip";
+
+impl EpubVersion {
+    fn template(&self, name: &str) -> String {
+        match self {
+            EpubVersion::Epub2 => format!("epub/{}", name),
+            EpubVersion::Epub3 => format!("epub3/{}", name),
+        }
+    }
+}
+
+/// The UTC date and time of a number of seconds since the Unix epoch, as
+/// year, month, day, hours, minutes and seconds.
+fn civil_time(secs: u64) -> (i64, i64, i64, u64, u64, u64) {
+    let days = (secs / 86400) as i64;
+    let rem = secs % 86400;
+
+    // Civil calendar from a day count, see Howard Hinnant’s `civil_from_days`
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    (year, month, day, rem / 3600, (rem % 3600) / 60, rem % 60)
+}
+
+/// Format a number of seconds since the Unix epoch as a UTC date, in the
+/// `CCYY-MM-DDThh:mm:ssZ` form expected by `dcterms:modified`.
+pub(crate) fn format_timestamp(secs: u64) -> String {
+    let (year, month, day, hours, minutes, seconds) = civil_time(secs);
+
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year, month, day, hours, minutes, seconds
+    )
+}
+
+/// Read the `SOURCE_DATE_EPOCH` environment variable, which asks for a
+/// reproducible build dated from this number of seconds since the Unix
+/// epoch (see <https://reproducible-builds.org/specs/source-date-epoch/>).
+pub fn source_date_epoch() -> Result<Option<u64>, Error> {
+    match env::var("SOURCE_DATE_EPOCH") {
+        Ok(epoch) => epoch
+            .trim()
+            .parse()
+            .map(Some)
+            .or_raise("SOURCE_DATE_EPOCH is not a number of seconds"),
+        Err(_) => Ok(None),
+    }
+}
+
+/// The date a book is built at: `SOURCE_DATE_EPOCH` if set, the current
+/// time otherwise.
+pub(crate) fn build_timestamp() -> Result<u64, Error> {
+    match source_date_epoch()? {
+        Some(epoch) => Ok(epoch),
+        None => SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|now| now.as_secs())
+            .or_raise("System clock is set before the Unix epoch"),
+    }
+}
+
+fn chapter_entry(chapter: &Chapter<Html>, idx: usize, play_order: &mut usize) -> Value {
+    *play_order += 1;
+
+    json!({
+        "id": format!("ch{}", idx),
+        "href": format!("Text/{}.xhtml", idx),
+        "label": chapter
+            .title
+            .as_ref()
+            .map(|t| format!("{}. {}", idx + 1, t))
+            .unwrap_or_else(|| format!("{}", idx + 1)),
+        "play_order": play_order,
+        "children": [],
+    })
+}
+
+fn part_entry(part: &Part<Html>, idx: usize, ofs: usize, play_order: &mut usize) -> Value {
+    *play_order += 1;
+
+    let number = roman::to((idx + 1) as i32).unwrap_or_else(|| format!("{}", idx + 1));
+    let label = part
+        .title
+        .as_ref()
+        .map(|t| format!("{}. {}", number, t))
+        .unwrap_or(number);
+    let part_order = *play_order;
+
+    let children: Vec<Value> = part
+        .content
+        .iter()
+        .enumerate()
+        .map(|(chap_idx, chapter)| chapter_entry(chapter, ofs + chap_idx, play_order))
+        .collect();
+
+    json!({
+        "id": format!("p{}", idx),
+        "href": format!("Text/p{}.xhtml", idx),
+        "label": label,
+        "play_order": part_order,
+        "children": children,
+    })
+}
+
+/// The table of contents of the book, in reading order.  Parts are
+/// top-level entries whose `children` are their chapters.
+fn table_of_contents(content: &Content<Html>) -> Vec<Value> {
+    let mut play_order = 0;
+
+    match content {
+        Content::WithChapters(ref chapters) => chapters
+            .iter()
+            .enumerate()
+            .map(|(idx, chapter)| chapter_entry(chapter, idx, &mut play_order))
+            .collect(),
+        Content::WithParts(ref parts) => {
+            let mut ofs = 0;
+
+            parts
+                .iter()
+                .enumerate()
+                .map(|(idx, part)| {
+                    let entry = part_entry(part, idx, ofs, &mut play_order);
+                    ofs += part.content.len();
+                    entry
+                })
+                .collect()
+        }
+    }
+}
+
+/// The documents of the spine, that is the table of contents flattened.
+fn spine(toc: &[Value]) -> Vec<Value> {
+    let mut res = vec![];
+
+    for entry in toc {
+        res.push(json!({ "id": entry["id"], "href": entry["href"] }));
+
+        if let Some(children) = entry["children"].as_array() {
+            res.append(&mut spine(children));
+        }
+    }
+
+    res
+}
 
 pub trait EpubWriter: BookWriter {
     fn create_mimetype(&mut self) -> Result<(), Error> {
@@ -39,7 +198,10 @@ pub trait EpubWriter: BookWriter {
         chapters: Vec<&Chapter<Html>>,
         numbering: bool,
         lang: &Language,
+        version: &EpubVersion,
     ) -> Result<(), Error> {
+        let template = version.template("chapter.xhtml");
+
         chapters
             .iter()
             .enumerate()
@@ -55,7 +217,7 @@ pub trait EpubWriter: BookWriter {
                 self.write_template(
                     &PathBuf::from(format!("OEBPS/Text/{}", path)),
                     tera,
-                    "epub/chapter.xhtml",
+                    &template,
                     &ctx,
                 )?;
 
@@ -66,29 +228,127 @@ pub trait EpubWriter: BookWriter {
         Ok(())
     }
 
-    fn install_fonts(&mut self, assets: &PathBuf, fonts: &Vec<&str>) -> Result<(), Error> {
-        for f in fonts {
-            let src = fonts_dir(assets)?.join(f);
-            let dst = PathBuf::from("OEBPS/Fonts").join(f);
-
-            self.write_file(&dst, &src)?;
+    fn create_parts(
+        &mut self,
+        tera: &Tera,
+        parts: &[Part<Html>],
+        numbering: bool,
+        lang: &Language,
+        version: &EpubVersion,
+    ) -> Result<(), Error> {
+        let template = version.template("part.xhtml");
+
+        for (idx, part) in parts.iter().enumerate() {
+            let mut ctx = Context::new();
+            ctx.insert("title", &part.title);
+            ctx.insert("number", &(idx + 1));
+            ctx.insert("numbering", &numbering);
+            ctx.insert("language", lang);
+
+            self.write_template(
+                &PathBuf::from(format!("OEBPS/Text/p{}.xhtml", idx)),
+                tera,
+                &template,
+                &ctx,
+            )?;
         }
 
         Ok(())
     }
 
-    fn install_cover(&mut self, cover: &Cover) -> Result<(), Error> {
+    /// Copy the font files into the archive, and describe them for the
+    /// manifest and the `@font-face` rules of the stylesheet.  When `chars`
+    /// is set, TTF and OTF files are reduced to the glyphs of these
+    /// characters.
+    fn install_fonts(
+        &mut self,
+        assets: &dyn Assets,
+        fonts: &[FontFamily],
+        version: &EpubVersion,
+        chars: Option<&BTreeSet<char>>,
+    ) -> Result<Vec<Value>, Error> {
+        let mut res = vec![];
+        let mut names = HashSet::new();
+
+        for family in fonts {
+            for face in &family.faces {
+                let name = face.file_name()?;
+
+                // All the faces are written in the same directory
+                if !names.insert(name.clone()) {
+                    return Err(Error::new(&format!(
+                        "several font faces are named {:?}, rename one of them",
+                        name
+                    )));
+                }
+
+                let dst = PathBuf::from("OEBPS/Fonts").join(&name);
+
+                let data = face.load(assets)?;
+                let media_type = face.media_type(version)?;
+
+                match chars {
+                    Some(chars) if !media_type.contains("woff") => {
+                        let subset = subset_font(&data, chars).map_err(|e| Error::Other {
+                            message: format!("Could not subset {:?}", face.file),
+                            source: Some(Box::new(e)),
+                        })?;
+
+                        self.write_bytes(&dst, &subset)?;
+                    }
+                    _ => self.write_bytes(&dst, &data)?,
+                }
+
+                res.push(json!({
+                    "id": format!("font{}", res.len()),
+                    "file": name,
+                    "media_type": media_type,
+                    "family": family.family,
+                    "weight": face.weight,
+                    "style": face.style,
+                }));
+            }
+        }
+
+        Ok(res)
+    }
+
+    fn install_cover(
+        &mut self,
+        tera: &Tera,
+        cover: &Cover,
+        title: &str,
+        lang: &Language,
+        version: &EpubVersion,
+    ) -> Result<(), Error> {
         let dst = PathBuf::from("OEBPS").join(format!("cover.{}", cover.extension));
-        self.write_bytes(&dst, cover.content.as_slice())
+        self.write_bytes(&dst, cover.content.as_slice())?;
+
+        let mut ctx = Context::new();
+        ctx.insert("cover", &format!("../cover.{}", cover.extension));
+        ctx.insert("title", title);
+        ctx.insert("language", lang);
+
+        self.write_template(
+            &PathBuf::from("OEBPS/Text/cover.xhtml"),
+            tera,
+            &version.template("cover.xhtml"),
+            &ctx,
+        )
     }
 
+    /// Generate an EPUB from a rendered project.  Templates found in the
+    /// `templates` directory, if any, override the ones of the assets.
     fn generate_epub(
         &mut self,
         project: &Project<Cover, Html>,
-        assets: &PathBuf,
+        assets: &dyn Assets,
+        templates: Option<&Path>,
     ) -> Result<(), Error> {
-        let tera =
-            Tera::new(template_dir(assets)?.as_str()).or_raise("Could not build templates")?;
+        let mut tera = load_templates(assets, templates)?;
+        tera.register_filter("roman", roman_filter);
+
+        let version = project.epub_version.as_ref().unwrap_or(&EpubVersion::Epub2);
 
         self.create_mimetype()?;
         self.create_container(&tera)?;
@@ -98,142 +358,491 @@ pub trait EpubWriter: BookWriter {
             project.content.chapters(),
             project.numbering.unwrap_or(false),
             &project.language,
+            version,
         )?;
 
-        self.write_template(
-            &PathBuf::from("OEBPS/Style/main.css"),
-            &tera,
-            "epub/main.css",
-            &Context::new(),
-        )?;
+        if let Content::WithParts(ref parts) = project.content {
+            self.create_parts(
+                &tera,
+                parts,
+                project.numbering.unwrap_or(false),
+                &project.language,
+                version,
+            )?;
+        }
+
+        let cover = match project.cover {
+            Some(ref cov) => {
+                let media_type = cov.media_type()?;
+
+                if let (EpubVersion::Epub2, "image/webp") = (version, media_type) {
+                    return Err(Error::new("WebP covers are only supported by EPUB 3"));
+                }
+
+                self.install_cover(&tera, cov, &project.title, &project.language, version)?;
+
+                Some(json!({
+                    "href": format!("cover.{}", cov.extension),
+                    "media_type": media_type,
+                }))
+            }
+            None => None,
+        };
+
+        let default_fonts;
+        let families = match project.fonts {
+            Some(ref fonts) => fonts,
+            None => {
+                default_fonts = FontFamily::defaults();
+                &default_fonts
+            }
+        };
+
+        let chars = if project.subset_fonts.unwrap_or(false) {
+            Some(used_chars(project))
+        } else {
+            None
+        };
+
+        let fonts = self.install_fonts(assets, families, version, chars.as_ref())?;
+
+        let mut ctx = Context::new();
+        ctx.insert("fonts", &fonts);
+        ctx.insert(
+            "families",
+            &families.iter().map(|f| &f.family).collect::<Vec<_>>(),
+        );
 
-        if let Some(ref cov) = project.cover {
-            self.install_cover(cov)?;
+        let mut style = tera
+            .render("epub/main.css", &ctx)
+            .map_err(|e| Error::template(Some("epub/main.css"), e))?;
+
+        if let (Some(true), Some(characters)) =
+            (project.epub_character_styles, project.characters.as_ref())
+        {
+            style.push_str(&stylesheet(characters)?);
         }
 
-        let fonts = vec![
-            "et-book-roman-line-figures.ttf",
-            "et-book-bold-line-figures.ttf",
-            "et-book-display-italic-old-style-figures.ttf",
-        ];
+        self.write_bytes(&PathBuf::from("OEBPS/Style/main.css"), style.as_bytes())?;
 
-        self.install_fonts(assets, &fonts)?;
+        let toc = table_of_contents(&project.content);
+        let mut files = spine(&toc);
 
-        let files = project
-            .content
-            .chapters()
-            .iter()
-            .enumerate()
-            .map(|(idx, _)| idx)
-            .collect::<Vec<usize>>();
+        if cover.is_some() {
+            files.insert(0, json!({ "id": "cover", "href": "Text/cover.xhtml" }));
+        }
+
+        let identifier = project.identifier();
 
         let mut ctx = Context::new();
         ctx.insert("title", &project.title);
         ctx.insert("author", &project.author);
         ctx.insert(
-            "cover_extension",
-            &project.cover.as_ref().map(|x| x.extension.clone()),
+            "identifier",
+            &json!({
+                "scheme": identifier.scheme(),
+                "urn": identifier.urn(),
+            }),
         );
+        ctx.insert("description", &project.description);
+        ctx.insert("metadata", &project.metadata);
+        ctx.insert("cover", &cover);
         ctx.insert("files", &files);
         ctx.insert("fonts", &fonts);
         ctx.insert("language", &project.language);
 
+        if let EpubVersion::Epub3 = version {
+            ctx.insert("modified", &format_timestamp(build_timestamp()?));
+        }
+
         self.write_template(
             &PathBuf::from("OEBPS/content.opf"),
             &tera,
-            "epub/content.opf",
+            &version.template("content.opf"),
             &ctx,
         )?;
 
-        let chaps: Vec<_> = project
-            .content
-            .chapters()
-            .iter()
-            .enumerate()
-            .map(|(idx, chapter)| {
-                json!({
-                    "index": idx,
-                    "title": chapter.title,
-                })
-            })
-            .collect();
+        let depth = match project.content {
+            Content::WithParts(_) => 2,
+            Content::WithChapters(_) => 1,
+        };
 
         let mut ctx = Context::new();
-        ctx.insert("chapters", &chaps);
+        ctx.insert("identifier", &identifier.urn());
+        ctx.insert("toc", &toc);
+        ctx.insert("depth", &depth);
         self.write_template(&PathBuf::from("OEBPS/toc.ncx"), &tera, "epub/toc.ncx", &ctx)?;
 
+        if let EpubVersion::Epub3 = version {
+            ctx.insert("title", &project.title);
+            ctx.insert("language", &project.language);
+            self.write_template(
+                &PathBuf::from("OEBPS/nav.xhtml"),
+                &tera,
+                "epub3/nav.xhtml",
+                &ctx,
+            )?;
+        }
+
         Ok(())
     }
 }
 
 impl<W> EpubWriter for W where W: BookWriter {}
 
-pub struct Zip {
-    output: ZipWriter<File>,
+pub struct Zip<W: Write + Seek> {
+    output: ZipWriter<W>,
     dirs: HashSet<PathBuf>,
+    /// The modification time of the entries, in reproducible mode
+    timestamp: Option<DateTime>,
+    /// The entries waiting to be written by `finish`, in reproducible mode
+    pending: Option<BTreeMap<PathBuf, Vec<u8>>>,
+}
+
+impl Zip<File> {
+    pub fn create(path: &Path) -> Result<Zip<File>, Error> {
+        let file = File::create(path).map_err(|e| Error::io("create", path, e))?;
+
+        Ok(Zip::new(file))
+    }
+}
+
+/// The date of the entries of an archive built `epoch` seconds after the
+/// Unix epoch.  ZIP archives cannot store dates before 1980, which are
+/// replaced by the first of January, 1980.
+fn dos_time(epoch: u64) -> DateTime {
+    let (year, month, day, hours, minutes, seconds) = civil_time(epoch);
+
+    DateTime::from_date_and_time(
+        year as u16,
+        month as u8,
+        day as u8,
+        hours as u8,
+        minutes as u8,
+        seconds as u8,
+    )
+    .unwrap_or_default()
 }
 
-impl Zip {
-    pub fn init() -> Result<Zip, Error> {
-        let file = File::create("Book.epub").or_raise("Could not create Book.epub")?;
+/// Whether an entry should be stored uncompressed: the OCF specification
+/// requires it for `mimetype`, and compressing images or WOFF fonts is
+/// pointless.
+fn is_stored(dst: &Path) -> bool {
+    let extension = dst
+        .extension()
+        .and_then(|x| x.to_str())
+        .map(|x| x.to_lowercase());
+
+    dst == Path::new("mimetype")
+        || matches!(
+            extension.as_deref(),
+            Some("jpg" | "jpeg" | "png" | "gif" | "webp" | "woff" | "woff2")
+        )
+}
 
-        Ok(Zip {
-            output: ZipWriter::new(file),
+impl<W: Write + Seek> Zip<W> {
+    pub fn new(output: W) -> Zip<W> {
+        Zip {
+            output: ZipWriter::new(output),
             dirs: HashSet::new(),
-        })
+            timestamp: None,
+            pending: None,
+        }
+    }
+
+    /// Make the archive reproducible: its entries are written in a fixed
+    /// order (`mimetype` first, then by name) when the archive is finished,
+    /// and are all dated from `epoch` seconds since the Unix epoch.
+    pub fn reproducible(mut self, epoch: u64) -> Zip<W> {
+        self.timestamp = Some(dos_time(epoch));
+        self.pending = Some(BTreeMap::new());
+        self
     }
 
-    fn create_parent(&mut self, dst: &PathBuf) -> Result<(), Error> {
+    fn options(&self, dst: &Path, permissions: u32) -> FileOptions {
+        let method = if is_stored(dst) {
+            CompressionMethod::Stored
+        } else {
+            CompressionMethod::Deflated
+        };
+
+        let options = FileOptions::default().compression_method(method);
+
+        match self.timestamp {
+            Some(timestamp) => options
+                .last_modified_time(timestamp)
+                .unix_permissions(permissions),
+            None => options,
+        }
+    }
+
+    /// Write the central directory of the archive, and give back the
+    /// underlying output.
+    pub fn finish(mut self) -> Result<W, Error> {
+        if let Some(mut pending) = self.pending.take() {
+            let mimetype = PathBuf::from("mimetype");
+
+            if let Some(content) = pending.remove(&mimetype) {
+                self.write_entry(&mimetype, &content)?;
+            }
+
+            for (dst, content) in pending {
+                self.write_entry(&dst, &content)?;
+            }
+        }
+
+        self.output
+            .finish()
+            .or_raise("Could not finalize the archive")
+    }
+
+    fn create_parent(&mut self, dst: &Path) -> Result<(), Error> {
         if let Some(dir) = dst.parent() {
-            if self.dirs.contains(dir) {
-                if let Some(dir_str) = dir.to_str() {
-                    self.output
-                        .add_directory(dir_str, FileOptions::default())
-                        .or_raise(&format!("Could not create directory {:?}", dir))?;
-                    self.dirs.insert(dir.to_path_buf());
-                }
+            if dir.as_os_str().is_empty() || self.dirs.contains(dir) {
+                return Ok(());
+            }
+
+            self.create_parent(dir)?;
+
+            if let Some(dir_str) = dir.to_str() {
+                let options = self.options(dir, 0o755);
+
+                self.output
+                    .add_directory(dir_str, options)
+                    .map_err(|source| Error::Archive {
+                        path: dir.to_path_buf(),
+                        source,
+                    })?;
+                self.dirs.insert(dir.to_path_buf());
             }
         }
 
         Ok(())
     }
-}
 
-impl BookWriter for Zip {
-    fn write_bytes(&mut self, dst: &PathBuf, input: &[u8]) -> Result<(), Error> {
+    fn write_entry(&mut self, dst: &Path, input: &[u8]) -> Result<(), Error> {
         self.create_parent(dst)?;
 
-        if let Some(dst) = dst.to_str() {
-            self.output
-                .start_file(dst, FileOptions::default())
-                .or_raise(&format!("Could not add file {:?} to archive", dst))?;
+        if let Some(dst_str) = dst.to_str() {
+            let options = self.options(dst, 0o644);
 
             self.output
-                .write_all(input)
-                .or_raise(&format!("Could not write {:?} content", dst))?;
+                .start_file(dst_str, options)
+                .map_err(|source| Error::Archive {
+                    path: dst.to_path_buf(),
+                    source,
+                })?;
+
+            self.output.write_all(input).map_err(|e| Error::Archive {
+                path: dst.to_path_buf(),
+                source: ZipError::Io(e),
+            })?;
         }
 
         Ok(())
     }
+}
 
-    fn write_file(&mut self, dst: &PathBuf, src: &PathBuf) -> Result<(), Error> {
-        if let Some(dst_str) = dst.to_str() {
-            let mut buffer = Vec::new();
-            let mut f = File::open(src).or_raise(&format!("Could not open {:?}", src))?;
-            f.read_to_end(&mut buffer)
-                .or_raise(&format!("Could not read {:?} content", src))?;
+impl<W: Write + Seek> BookWriter for Zip<W> {
+    fn write_bytes(&mut self, dst: &Path, input: &[u8]) -> Result<(), Error> {
+        match self.pending {
+            Some(ref mut pending) => {
+                pending.insert(dst.to_path_buf(), input.to_vec());
+                Ok(())
+            }
+            None => self.write_entry(dst, input),
+        }
+    }
 
-            self.create_parent(dst)?;
+    fn write_file(&mut self, dst: &Path, src: &Path) -> Result<(), Error> {
+        let mut buffer = Vec::new();
+        let mut f = File::open(src).map_err(|e| Error::io("open", src, e))?;
+        f.read_to_end(&mut buffer)
+            .map_err(|e| Error::io("read", src, e))?;
 
-            self.output
-                .start_file(dst_str, FileOptions::default())
-                .or_raise(&format!("Could not add file {:?} to archive", dst))?;
+        self.write_bytes(dst, &buffer)
+    }
+}
 
-            self.output
-                .write_all(buffer.as_ref())
-                .or_raise(&format!("Could not write {:?} content", dst))?;
+#[cfg(test)]
+mod tests {
+    use super::{dos_time, source_date_epoch, EpubWriter, Zip};
+    use crate::assets::Embedded;
+    use crate::fonts::{FontFace, FontFamily};
+    use crate::project::{Chapter, Content, EpubVersion, Language, Part, Project};
+    use crate::render::Html;
+    use crate::validate::validate_epub;
+    use ogam::typography::FRENCH;
+    use std::io::{Cursor, Read};
+    use std::path::PathBuf;
+    use zip::{CompressionMethod, DateTime, ZipArchive};
+
+    fn project(version: EpubVersion) -> Project<super::Cover, Html> {
+        let document = || ogam::compile::<Html, _>("Fish & chips.", &FRENCH).unwrap();
+
+        Project {
+            author: String::from("Jack & Jill"),
+            title: String::from("Fish & <Chips>"),
+            description: None,
+            cover: None,
+            numbering: Some(true),
+            language: Language::Fr,
+            epub_version: Some(version),
+            metadata: None,
+            fonts: Some(vec![]),
+            subset_fonts: None,
+            characters: None,
+            epub_character_styles: None,
+            content: Content::WithParts(vec![Part {
+                title: Some(String::from("Première & partie")),
+                content: vec![
+                    Chapter {
+                        title: Some(String::from("Premier <chapitre>")),
+                        content: vec![document()],
+                    },
+                    Chapter {
+                        title: None,
+                        content: vec![document()],
+                    },
+                ],
+            }]),
         }
+    }
 
-        Ok(())
+    #[test]
+    fn escapes_titles() {
+        for version in [EpubVersion::Epub2, EpubVersion::Epub3] {
+            let mut zip = Zip::new(Cursor::new(vec![]));
+            zip.generate_epub(&project(version), &Embedded, None)
+                .unwrap();
+            let mut output = zip.finish().unwrap();
+            output.set_position(0);
+
+            let violations = validate_epub(output).unwrap();
+
+            assert!(violations.is_empty(), "{:?}", violations);
+        }
+    }
+
+    #[test]
+    fn converts_dates_to_dos_time() {
+        let date = |t: DateTime| {
+            (
+                t.year(),
+                t.month(),
+                t.day(),
+                t.hour(),
+                t.minute(),
+                t.second(),
+            )
+        };
+
+        assert_eq!(date(dos_time(1_700_000_000)), (2023, 11, 14, 22, 13, 20));
+        assert_eq!(date(dos_time(951_782_400)), (2000, 2, 29, 0, 0, 0));
+        assert_eq!(date(dos_time(315_532_800)), (1980, 1, 1, 0, 0, 0));
+        // Dates before 1980 cannot be stored
+        assert_eq!(date(dos_time(0)), (1980, 1, 1, 0, 0, 0));
+        assert_eq!(date(dos_time(315_532_799)), (1980, 1, 1, 0, 0, 0));
+    }
+
+    #[test]
+    fn builds_reproducible_archives() {
+        std::env::set_var("SOURCE_DATE_EPOCH", "1700000000");
+        let epoch = source_date_epoch().unwrap().unwrap();
+
+        let build = || {
+            let mut zip = Zip::new(Cursor::new(vec![])).reproducible(epoch);
+            zip.generate_epub(&project(EpubVersion::Epub3), &Embedded, None)
+                .unwrap();
+            zip.finish().unwrap().into_inner()
+        };
+
+        let output = build();
+        assert_eq!(output, build());
+
+        let mut archive = ZipArchive::new(Cursor::new(output)).unwrap();
+
+        for idx in 0..archive.len() {
+            let entry = archive.by_index(idx).unwrap();
+            let date = entry.last_modified();
+
+            assert_eq!(date.datepart(), dos_time(epoch).datepart());
+            assert_eq!(date.timepart(), dos_time(epoch).timepart());
+        }
+
+        let mimetype = archive.by_index(0).unwrap();
+
+        assert_eq!(mimetype.name(), "mimetype");
+        assert_eq!(mimetype.compression(), CompressionMethod::Stored);
+    }
+
+    fn face(file: PathBuf) -> FontFace {
+        FontFace {
+            file,
+            weight: String::from("normal"),
+            style: String::from("normal"),
+        }
+    }
+
+    fn read_entry(output: &mut Cursor<Vec<u8>>, name: &str) -> String {
+        let mut archive = ZipArchive::new(output).unwrap();
+        let mut res = String::new();
+        archive
+            .by_name(name)
+            .unwrap()
+            .read_to_string(&mut res)
+            .unwrap();
+        res
+    }
+
+    #[test]
+    fn encodes_font_file_names() {
+        let dir = std::env::temp_dir().join("celtchar-encodes-font-file-names");
+        std::fs::create_dir_all(&dir).unwrap();
+        let file = dir.join("my font.ttf");
+        std::fs::write(&file, b"not really a font").unwrap();
+
+        let project = Project {
+            fonts: Some(vec![FontFamily {
+                family: String::from("mine"),
+                faces: vec![face(file)],
+            }]),
+            ..project(EpubVersion::Epub3)
+        };
+
+        let mut zip = Zip::new(Cursor::new(vec![]));
+        zip.generate_epub(&project, &Embedded, None).unwrap();
+        let mut output = zip.finish().unwrap();
+
+        assert!(read_entry(&mut output, "OEBPS/content.opf")
+            .contains(r#"<item href="Fonts/my%20font.ttf""#));
+        assert!(read_entry(&mut output, "OEBPS/Style/main.css")
+            .contains(r#"src: url("../Fonts/my%20font.ttf");"#));
+        read_entry(&mut output, "OEBPS/Fonts/my font.ttf");
+
+        output.set_position(0);
+        let violations = validate_epub(output).unwrap();
+
+        assert!(violations.is_empty(), "{:?}", violations);
+    }
+
+    #[test]
+    fn rejects_fonts_with_the_same_name() {
+        let project = Project {
+            fonts: Some(vec![FontFamily {
+                family: String::from("mine"),
+                faces: vec![
+                    face(PathBuf::from("et-book-roman-line-figures.ttf")),
+                    face(PathBuf::from("bold/et-book-roman-line-figures.ttf")),
+                ],
+            }]),
+            ..project(EpubVersion::Epub2)
+        };
+
+        let mut zip = Zip::new(Cursor::new(vec![]));
+
+        let err = zip.generate_epub(&project, &Embedded, None).unwrap_err();
+
+        assert!(err.to_string().contains("several font faces"), "{}", err);
     }
 }
@@ -1,9 +1,128 @@
+use std::error::Error as StdError;
+use std::fmt;
+use std::io;
+use std::path::PathBuf;
+
+use crate::diagnostics::Diagnostic;
+
+/// The underlying error of a failure.
+pub type Cause = Box<dyn StdError + Send + Sync>;
+
 #[derive(Debug)]
-pub struct Error(pub String);
+pub enum Error {
+    /// The project file could not be read or is ill-formed.
+    Project {
+        path : PathBuf,
+        /// The line and column of the faulty entry, when known
+        location : Option<(usize, usize)>,
+        source : Cause,
+    },
+    /// A file (document, cover, font, output…) could not be accessed.
+    Io {
+        /// What we were trying to do, *e.g.*, `read` or `create`
+        operation : &'static str,
+        path : PathBuf,
+        source : io::Error,
+    },
+    /// ogam documents could not be parsed, or contain ill-formed content
+    /// while building in strict mode.
+    Parse(Vec<Diagnostic>),
+    /// A template could not be parsed or rendered.
+    Template {
+        /// The template being rendered, if any
+        name : Option<String>,
+        source : tera::Error,
+    },
+    /// A file could not be added to an archive.
+    Archive {
+        path : PathBuf,
+        source : zip::result::ZipError,
+    },
+    /// Any other failure, with its cause if there is one.
+    Other {
+        message : String,
+        source : Option<Cause>,
+    },
+}
 
 impl Error {
     pub fn new(str : &str) -> Error {
-        Error(String::from(str))
+        Error::Other {
+            message : String::from(str),
+            source : None,
+        }
+    }
+
+    pub fn io<P : Into<PathBuf>>(operation : &'static str, path : P, source : io::Error) -> Error {
+        Error::Io {
+            operation,
+            path : path.into(),
+            source,
+        }
+    }
+
+    pub fn template(name : Option<&str>, source : tera::Error) -> Error {
+        Error::Template {
+            name : name.map(String::from),
+            source,
+        }
+    }
+
+    /// Iterate over the causes of an error, from the closest to the root.
+    pub fn causes(&self) -> impl Iterator<Item = &(dyn StdError + 'static)> {
+        let mut next = self.source();
+
+        std::iter::from_fn(move || {
+            let current = next?;
+            next = current.source();
+            Some(current)
+        })
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f : &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Project { path, location : Some((line, column)), .. } => {
+                write!(f, "{}:{}:{}: could not load the project", path.display(), line, column)
+            }
+            Error::Project { path, .. } => {
+                write!(f, "{}: could not load the project", path.display())
+            }
+            Error::Io { operation, path, .. } => {
+                write!(f, "could not {} {}", operation, path.display())
+            }
+            Error::Parse(diagnostics) => {
+                write!(f, "{} issue(s) in ogam documents", diagnostics.len())?;
+
+                for diagnostic in diagnostics {
+                    write!(f, "\n\n{}", diagnostic)?;
+                }
+
+                Ok(())
+            }
+            Error::Template { name : Some(name), .. } => {
+                write!(f, "could not render template {}", name)
+            }
+            Error::Template { .. } => write!(f, "could not build templates"),
+            Error::Archive { path, .. } => {
+                write!(f, "could not add {} to the archive", path.display())
+            }
+            Error::Other { message, .. } => write!(f, "{}", message),
+        }
+    }
+}
+
+impl StdError for Error {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        match self {
+            Error::Project { source, .. } => Some(source.as_ref()),
+            Error::Io { source, .. } => Some(source),
+            Error::Parse(_) => None,
+            Error::Template { source, .. } => Some(source),
+            Error::Archive { source, .. } => Some(source),
+            Error::Other { source, .. } => source.as_ref().map(|x| x.as_ref() as _),
+        }
     }
 }
 
@@ -17,14 +136,20 @@ impl<T> Raise for Option<T> {
     type Out = Result<T, Error>;
 
     fn or_raise(self, msg : &str) -> Result<T, Error> {
-        self.ok_or(Error(String::from(msg)))
+        self.ok_or_else(|| Error::new(msg))
     }
 }
 
-impl<T, E> Raise for Result<T, E> {
+impl<T, E> Raise for Result<T, E>
+where
+    E : StdError + Send + Sync + 'static,
+{
     type Out = Result<T, Error>;
 
     fn or_raise(self, msg : &str) -> Result<T, Error> {
-        self.map_err(|_| Error(String::from(msg)))
+        self.map_err(|e| Error::Other {
+            message : String::from(msg),
+            source : Some(Box::new(e)),
+        })
     }
 }
@@ -0,0 +1,201 @@
+use ogam::generator::Output;
+use ogam::typography::Space;
+
+use crate::render::{escape_xml, StoryBreaks};
+
+/// The content of a FictionBook section.  Scene breaks are rendered as
+/// subtitles, and asides as citations.
+pub struct Fb2 {
+    out: String,
+    /// Whether we are rendering a thought, whose replies are emphasized
+    thought: bool,
+    /// Whether we are rendering the lines of an ill-formed block, and
+    /// whether one was already rendered
+    illformed_block: Option<bool>,
+    breaks: StoryBreaks,
+}
+
+impl Fb2 {
+    pub fn as_str(&self) -> &str {
+        &self.out
+    }
+
+    pub fn into_string(self) -> String {
+        self.out
+    }
+
+    fn tag<F>(&mut self, tag: &str, format: F)
+    where
+        F: FnOnce(&mut Fb2),
+    {
+        self.out.push_str(&format!("<{}>", tag));
+        format(self);
+        self.out.push_str(&format!("</{}>", tag));
+    }
+
+    /// Like `tag`, but for inline styles: surrounding spaces are kept out
+    /// of the element, and nothing is emphasized if there is only space.
+    fn inline_tag<F>(&mut self, tag: &str, format: F)
+    where
+        F: FnOnce(&mut Fb2),
+    {
+        let start = self.out.len();
+        format(self);
+        let inner = self.out.split_off(start);
+
+        let content = inner.trim_start_matches(' ');
+        let lead = &inner[..inner.len() - content.len()];
+        let content = content.trim_end_matches(' ');
+        let trail = &inner[lead.len() + content.len()..];
+
+        self.out.push_str(lead);
+        if !content.is_empty() {
+            self.out
+                .push_str(&format!("<{}>{}</{}>", tag, content, tag));
+        }
+        self.out.push_str(trail);
+    }
+}
+
+impl Output for Fb2 {
+    fn empty(input_size: usize) -> Fb2 {
+        Fb2 {
+            out: String::with_capacity(input_size + input_size / 2),
+            thought: false,
+            illformed_block: None,
+            breaks: StoryBreaks::default(),
+        }
+    }
+
+    fn render_space(&mut self, space: Space) {
+        self.out.push_str(match space {
+            Space::Normal => " ",
+            Space::Nbsp => "\u{a0}",
+            Space::None => "",
+        })
+    }
+
+    fn render_word(&mut self, word: &str) {
+        escape_xml(word, &mut self.out)
+    }
+
+    fn render_mark(&mut self, mark: &str) {
+        escape_xml(mark, &mut self.out)
+    }
+
+    fn render_illformed(&mut self, err: &str) {
+        // Each line of an ill-formed block is given a paragraph
+        if let Some(ref mut started) = self.illformed_block {
+            if *started {
+                self.out.push_str("</p><p>");
+            }
+            *started = true;
+        }
+        escape_xml(err, &mut self.out)
+    }
+
+    fn emph_template<F>(&mut self, format: F)
+    where
+        F: FnOnce(&mut Fb2),
+    {
+        self.inline_tag("emphasis", format);
+    }
+
+    fn strong_emph_template<F>(&mut self, format: F)
+    where
+        F: FnOnce(&mut Fb2),
+    {
+        self.inline_tag("strong", format);
+    }
+
+    fn reply_template<F>(&mut self, reply: F, _author: &Option<&str>)
+    where
+        F: FnOnce(&mut Fb2),
+    {
+        if self.thought {
+            self.inline_tag("emphasis", reply);
+        } else {
+            reply(self);
+        }
+    }
+
+    fn thought_template<F>(&mut self, reply: F, _author: &Option<&str>)
+    where
+        F: FnOnce(&mut Fb2),
+    {
+        self.thought = true;
+        reply(self);
+        self.thought = false;
+    }
+
+    fn dialogue_template<F>(&mut self, reply: F, _author: &Option<&str>)
+    where
+        F: FnOnce(&mut Fb2),
+    {
+        reply(self);
+    }
+
+    fn between_dialogue(&mut self) {
+        self.out.push_str("</p><p>");
+    }
+
+    fn illformed_inline_template<F>(&mut self, err: F)
+    where
+        F: FnOnce(&mut Fb2),
+    {
+        err(self);
+    }
+
+    fn paragraph_template<F>(&mut self, para: F)
+    where
+        F: FnOnce(&mut Fb2),
+    {
+        self.tag("p", para);
+    }
+
+    fn illformed_block_template<F>(&mut self, err: F)
+    where
+        F: FnOnce(&mut Fb2),
+    {
+        self.illformed_block = Some(false);
+        self.tag("p", err);
+        self.illformed_block = None;
+        self.breaks.other();
+    }
+
+    fn story_template<F>(&mut self, story: F)
+    where
+        F: FnOnce(&mut Fb2),
+    {
+        if self.breaks.story() {
+            self.out.push_str("<subtitle>* * *</subtitle>");
+        }
+        story(self);
+    }
+
+    fn aside_template<F>(&mut self, _cls: &Option<&str>, aside: F)
+    where
+        F: FnOnce(&mut Fb2),
+    {
+        self.tag("cite", aside);
+        self.breaks.other();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Fb2;
+    use ogam::typography::ENGLISH;
+
+    #[test]
+    fn fb2_emphasizes_replies_in_thoughts() {
+        let res = ogam::compile::<Fb2, _>("<*Sure*, I +thought+.>", &ENGLISH)
+            .unwrap()
+            .into_string();
+
+        assert_eq!(
+            res,
+            "<p><emphasis><emphasis>Sure</emphasis>, I <strong>thought</strong>.</emphasis></p>"
+        );
+    }
+}
@@ -0,0 +1,94 @@
+use serde_derive::{Deserialize, Serialize};
+use std::fs::read;
+use std::path::PathBuf;
+
+use crate::assets::Assets;
+use crate::error::{Error, Raise};
+use crate::project::EpubVersion;
+
+fn normal() -> String {
+    String::from("normal")
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct FontFace {
+    /// Path to the font file.  Relative paths are looked up in the fonts
+    /// of the assets.
+    pub file: PathBuf,
+    #[serde(default = "normal")]
+    pub weight: String,
+    #[serde(default = "normal")]
+    pub style: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct FontFamily {
+    pub family: String,
+    pub faces: Vec<FontFace>,
+}
+
+impl FontFamily {
+    /// The fonts embedded when a project does not list its own: ET Book,
+    /// shipped with the assets of celtchar.
+    pub fn defaults() -> Vec<FontFamily> {
+        let face = |file: &str, weight: &str, style: &str| FontFace {
+            file: PathBuf::from(file),
+            weight: String::from(weight),
+            style: String::from(style),
+        };
+
+        vec![FontFamily {
+            family: String::from("et-book"),
+            faces: vec![
+                face("et-book-roman-line-figures.ttf", "normal", "normal"),
+                face("et-book-bold-line-figures.ttf", "bold", "normal"),
+                face(
+                    "et-book-display-italic-old-style-figures.ttf",
+                    "normal",
+                    "italic",
+                ),
+            ],
+        }]
+    }
+}
+
+impl FontFace {
+    pub fn load(&self, assets: &dyn Assets) -> Result<Vec<u8>, Error> {
+        if self.file.is_absolute() {
+            read(&self.file).map_err(|e| Error::io("read", &self.file, e))
+        } else {
+            assets.font(&self.file)
+        }
+    }
+
+    /// The name of the font file once embedded in a book.
+    pub fn file_name(&self) -> Result<String, Error> {
+        self.file
+            .file_name()
+            .and_then(|x| x.to_str())
+            .map(String::from)
+            .or_raise(&format!("{:?} is not a valid font file name", self.file))
+    }
+
+    pub fn media_type(&self, version: &EpubVersion) -> Result<&'static str, Error> {
+        let extension = self
+            .file
+            .extension()
+            .and_then(|x| x.to_str())
+            .map(|x| x.to_lowercase());
+
+        match (extension.as_deref(), version) {
+            (Some("ttf"), EpubVersion::Epub2) => Ok("application/x-font-ttf"),
+            (Some("otf"), EpubVersion::Epub2) => Ok("application/vnd.ms-opentype"),
+            (Some("woff"), EpubVersion::Epub2) => Ok("application/font-woff"),
+            (Some("ttf"), EpubVersion::Epub3) => Ok("font/ttf"),
+            (Some("otf"), EpubVersion::Epub3) => Ok("font/otf"),
+            (Some("woff"), EpubVersion::Epub3) => Ok("font/woff"),
+            (Some("woff2"), _) => Ok("font/woff2"),
+            _ => Err(Error::new(&format!(
+                "unsupported font file {:?}: expected a TTF, OTF, WOFF or WOFF2 file",
+                self.file
+            ))),
+        }
+    }
+}
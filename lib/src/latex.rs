@@ -0,0 +1,245 @@
+use ogam::generator::Output;
+use ogam::typography::Space;
+
+use crate::render::StoryBreaks;
+
+/// Escape the characters of `text` with a special meaning in LaTeX.
+pub fn escape_latex(text: &str) -> String {
+    let mut res = String::with_capacity(text.len());
+
+    for c in text.chars() {
+        match c {
+            '\\' => res.push_str("\\textbackslash{}"),
+            '~' => res.push_str("\\textasciitilde{}"),
+            '^' => res.push_str("\\textasciicircum{}"),
+            '{' | '}' | '$' | '&' | '%' | '#' | '_' => {
+                res.push('\\');
+                res.push(c);
+            }
+            c => res.push(c),
+        }
+    }
+
+    res
+}
+
+/// A LaTeX fragment, to be included in a document which defines the
+/// `aside` environment and the `\storybreak` command.
+pub struct Latex {
+    out: String,
+    /// A non-breaking space is due, whose width depends on what follows it
+    nbsp: bool,
+    /// Whether we are rendering a thought, whose replies are emphasized
+    thought: bool,
+    breaks: StoryBreaks,
+}
+
+impl Latex {
+    pub fn as_str(&self) -> &str {
+        &self.out
+    }
+
+    pub fn into_string(self) -> String {
+        self.out
+    }
+
+    /// Push markup, after the pending non-breaking space, if any.
+    fn push_str(&mut self, s: &str) {
+        if self.nbsp {
+            // French typography calls for a thin space before `;`, `!` and
+            // `?`, and for a regular one anywhere else.
+            let thin = s.starts_with([';', '!', '?']);
+            self.out.push_str(if thin { "\\," } else { "~" });
+            self.nbsp = false;
+        }
+
+        self.out.push_str(s);
+    }
+
+    fn push_text(&mut self, s: &str) {
+        self.push_str(&escape_latex(s));
+    }
+
+    /// Pass the content rendered by `format` to `command`.  Surrounding
+    /// spaces are kept out of the braces, and a non-breaking space due at
+    /// the end is left for what follows the command to decide its width.
+    fn command<F>(&mut self, command: &str, format: F)
+    where
+        F: FnOnce(&mut Latex),
+    {
+        // A space due before the command is not part of its content
+        self.push_str("");
+
+        let start = self.out.len();
+        format(self);
+        let inner = self.out.split_off(start);
+
+        let content = inner.trim_start_matches(' ');
+        let lead = &inner[..inner.len() - content.len()];
+        let content = content.trim_end_matches(' ');
+        let trail = &inner[lead.len() + content.len()..];
+
+        self.out.push_str(lead);
+        if !content.is_empty() {
+            self.out.push_str(command);
+            self.out.push('{');
+            self.out.push_str(content);
+            self.out.push('}');
+        }
+        self.out.push_str(trail);
+    }
+}
+
+impl Output for Latex {
+    fn empty(input_size: usize) -> Latex {
+        Latex {
+            out: String::with_capacity((12 * input_size) / 10),
+            nbsp: false,
+            thought: false,
+            breaks: StoryBreaks::default(),
+        }
+    }
+
+    fn render_space(&mut self, space: Space) {
+        match space {
+            Space::Normal => self.push_str(" "),
+            Space::Nbsp => self.nbsp = true,
+            Space::None => (),
+        }
+    }
+
+    fn render_word(&mut self, word: &str) {
+        self.push_text(word)
+    }
+
+    fn render_mark(&mut self, mark: &str) {
+        self.push_text(mark)
+    }
+
+    fn render_illformed(&mut self, err: &str) {
+        self.push_text(err)
+    }
+
+    fn emph_template<F>(&mut self, format: F)
+    where
+        F: FnOnce(&mut Latex),
+    {
+        self.command("\\emph", format);
+    }
+
+    fn strong_emph_template<F>(&mut self, format: F)
+    where
+        F: FnOnce(&mut Latex),
+    {
+        self.command("\\textbf", format);
+    }
+
+    fn reply_template<F>(&mut self, reply: F, _author: &Option<&str>)
+    where
+        F: FnOnce(&mut Latex),
+    {
+        if self.thought {
+            self.command("\\emph", reply);
+        } else {
+            reply(self);
+        }
+    }
+
+    fn thought_template<F>(&mut self, reply: F, _author: &Option<&str>)
+    where
+        F: FnOnce(&mut Latex),
+    {
+        self.thought = true;
+        reply(self);
+        self.thought = false;
+    }
+
+    fn dialogue_template<F>(&mut self, reply: F, _author: &Option<&str>)
+    where
+        F: FnOnce(&mut Latex),
+    {
+        reply(self);
+    }
+
+    fn between_dialogue(&mut self) {
+        self.push_str("\n\n");
+    }
+
+    fn illformed_inline_template<F>(&mut self, err: F)
+    where
+        F: FnOnce(&mut Latex),
+    {
+        err(self);
+    }
+
+    fn paragraph_template<F>(&mut self, para: F)
+    where
+        F: FnOnce(&mut Latex),
+    {
+        para(self);
+        self.nbsp = false;
+        self.push_str("\n\n");
+    }
+
+    fn illformed_block_template<F>(&mut self, err: F)
+    where
+        F: FnOnce(&mut Latex),
+    {
+        err(self);
+        self.breaks.other();
+        self.push_str("\n\n");
+    }
+
+    fn story_template<F>(&mut self, story: F)
+    where
+        F: FnOnce(&mut Latex),
+    {
+        if self.breaks.story() {
+            self.push_str("\\storybreak\n\n");
+        }
+        story(self);
+    }
+
+    fn aside_template<F>(&mut self, _cls: &Option<&str>, aside: F)
+    where
+        F: FnOnce(&mut Latex),
+    {
+        self.push_str("\\begin{aside}\n");
+        aside(self);
+        self.push_str("\\end{aside}\n\n");
+        self.breaks.other();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Latex;
+    use ogam::typography::{ENGLISH, FRENCH};
+
+    #[test]
+    fn latex_escapes_text() {
+        let res = ogam::compile::<Latex, _>("50% of $5 & co_op #1 {x}.", &ENGLISH)
+            .unwrap()
+            .into_string();
+
+        assert!(res.contains("50\\% of \\$5 \\& co\\_op \\#1 \\{x\\}."));
+    }
+
+    #[test]
+    fn latex_uses_french_spaces() {
+        let res = ogam::compile::<Latex, _>("[Vraiment ? Oui : non.]", &FRENCH)
+            .unwrap()
+            .into_string();
+
+        assert!(res.contains("«~Vraiment\\,? Oui~: non.~»"));
+    }
+
+    #[test]
+    fn latex_keeps_spaces_out_of_commands() {
+        let res = ogam::compile::<Latex, _>("A *very* fine +day+, <I think.>", &ENGLISH)
+            .unwrap()
+            .into_string();
+
+        assert!(res.contains("A \\emph{very} fine \\textbf{day}, \\emph{I think.}"));
+    }
+}
@@ -1,20 +1,56 @@
+extern crate allsorts;
 extern crate ogam;
+extern crate percent_encoding;
+extern crate roxmltree;
+extern crate serde;
 #[macro_use]
 extern crate serde_derive;
 extern crate serde_json;
 extern crate tera;
+extern crate uuid;
 extern crate zip;
 
 mod assets;
+mod characters;
+mod diagnostics;
+mod docx;
 mod epub;
 mod error;
+mod fb2;
+mod fonts;
+mod latex;
+mod markdown;
+mod metadata;
+mod odt;
 mod project;
 mod render;
+mod subset;
+mod text;
+mod utils;
+mod validate;
+mod wdocx;
+mod wfb2;
+mod wlatex;
+mod wmarkdown;
+mod wodt;
 mod writer;
 mod wstatic;
+mod wtext;
 
-pub use epub::{EpubWriter, Zip};
-pub use error::{Error, Raise};
-pub use project::{Chapter, Content, Cover, Loader, Part, Project};
+pub use assets::{Assets, Directory, Embedded};
+pub use characters::Character;
+pub use diagnostics::{Diagnostic, DiagnosticKind};
+pub use epub::{source_date_epoch, EpubWriter, Zip};
+pub use error::{Cause, Error, Raise};
+pub use fonts::{FontFace, FontFamily};
+pub use metadata::{Contributor, Identifier, Metadata, Series};
+pub use project::{Chapter, Content, Cover, EpubVersion, Language, Loader, Part, Project};
+pub use validate::{validate_epub, Violation};
+pub use wdocx::DocxWriter;
+pub use wfb2::Fb2Writer;
+pub use wlatex::LatexWriter;
+pub use wmarkdown::MarkdownWriter;
+pub use wodt::OdtWriter;
 pub use writer::BookWriter;
 pub use wstatic::Static;
+pub use wtext::TextWriter;
@@ -0,0 +1,264 @@
+use ogam::generator::Output;
+use ogam::typography::Space;
+
+use crate::render::StoryBreaks;
+
+/// Escape the characters of `text` with a special meaning in CommonMark,
+/// wherever they appear.
+pub fn escape_markdown(text: &str) -> String {
+    let mut res = String::with_capacity(text.len());
+
+    for c in text.chars() {
+        if is_markdown_special(c) {
+            res.push('\\');
+        }
+        res.push(c);
+    }
+
+    res
+}
+
+fn is_markdown_special(c: char) -> bool {
+    matches!(
+        c,
+        '\\' | '`' | '*' | '_' | '[' | ']' | '<' | '>' | '#' | '|' | '&' | '~'
+    )
+}
+
+/// A CommonMark document.
+pub struct Markdown {
+    out: String,
+    breaks: StoryBreaks,
+    /// Whether we are rendering the lines of an ill-formed block
+    illformed_block: bool,
+}
+
+impl Markdown {
+    pub fn as_str(&self) -> &str {
+        &self.out
+    }
+
+    pub fn into_string(self) -> String {
+        self.out
+    }
+
+    /// Push text, escaping the characters CommonMark would interpret,
+    /// including the ones only meaningful at the start of a line.
+    fn push_text(&mut self, s: &str) {
+        let line = &self.out[self.out.rfind('\n').map_or(0, |x| x + 1)..];
+        let line_start = line.trim().is_empty();
+        let numbered = !line.is_empty() && line.chars().all(|c| c.is_ascii_digit());
+
+        for (idx, c) in s.char_indices() {
+            let special = match c {
+                '-' | '+' | '=' => idx == 0 && line_start,
+                '.' | ')' => idx == 0 && numbered,
+                c => is_markdown_special(c),
+            };
+
+            if special {
+                self.out.push('\\');
+            }
+            self.out.push(c);
+        }
+    }
+
+    /// Render `format` and remove it from the output, to post-process it.
+    fn capture<F>(&mut self, format: F) -> String
+    where
+        F: FnOnce(&mut Markdown),
+    {
+        let start = self.out.len();
+        format(self);
+        self.out.split_off(start)
+    }
+
+    /// Surround the content rendered by `format` with `delim`.  CommonMark
+    /// does not allow emphasis to start or end with a space, so surrounding
+    /// spaces are kept out of the delimiters.
+    fn delimit<F>(&mut self, delim: &str, format: F)
+    where
+        F: FnOnce(&mut Markdown),
+    {
+        let inner = self.capture(format);
+        let content = inner.trim_start();
+        let lead = &inner[..inner.len() - content.len()];
+        let content = content.trim_end();
+        let trail = &inner[lead.len() + content.len()..];
+
+        self.out.push_str(lead);
+        if !content.is_empty() {
+            self.out.push_str(delim);
+            self.out.push_str(content);
+            self.out.push_str(delim);
+        }
+        self.out.push_str(trail);
+    }
+}
+
+impl Output for Markdown {
+    fn empty(input_size: usize) -> Markdown {
+        Markdown {
+            out: String::with_capacity((11 * input_size) / 10),
+            breaks: StoryBreaks::default(),
+            illformed_block: false,
+        }
+    }
+
+    fn render_space(&mut self, space: Space) {
+        self.out.push_str(match space {
+            Space::Normal => " ",
+            Space::Nbsp => "\u{a0}",
+            Space::None => "",
+        })
+    }
+
+    fn render_word(&mut self, word: &str) {
+        self.push_text(word)
+    }
+
+    fn render_mark(&mut self, mark: &str) {
+        self.push_text(mark)
+    }
+
+    fn render_illformed(&mut self, err: &str) {
+        // Each line of an ill-formed block is written on a line of its own
+        if self.illformed_block && !self.out.ends_with('\n') {
+            self.out.push('\n');
+        }
+        self.push_text(err)
+    }
+
+    fn emph_template<F>(&mut self, format: F)
+    where
+        F: FnOnce(&mut Markdown),
+    {
+        self.delimit("*", format);
+    }
+
+    fn strong_emph_template<F>(&mut self, format: F)
+    where
+        F: FnOnce(&mut Markdown),
+    {
+        self.delimit("**", format);
+    }
+
+    fn reply_template<F>(&mut self, reply: F, _author: &Option<&str>)
+    where
+        F: FnOnce(&mut Markdown),
+    {
+        reply(self);
+    }
+
+    fn thought_template<F>(&mut self, reply: F, _author: &Option<&str>)
+    where
+        F: FnOnce(&mut Markdown),
+    {
+        self.delimit("_", reply);
+    }
+
+    fn dialogue_template<F>(&mut self, reply: F, _author: &Option<&str>)
+    where
+        F: FnOnce(&mut Markdown),
+    {
+        reply(self);
+    }
+
+    fn between_dialogue(&mut self) {
+        self.out.push_str("\n\n");
+    }
+
+    fn illformed_inline_template<F>(&mut self, err: F)
+    where
+        F: FnOnce(&mut Markdown),
+    {
+        err(self);
+    }
+
+    fn paragraph_template<F>(&mut self, para: F)
+    where
+        F: FnOnce(&mut Markdown),
+    {
+        let para = self.capture(para);
+
+        self.out.push_str(para.trim());
+        self.out.push_str("\n\n");
+    }
+
+    fn illformed_block_template<F>(&mut self, err: F)
+    where
+        F: FnOnce(&mut Markdown),
+    {
+        self.illformed_block = true;
+        err(self);
+        self.illformed_block = false;
+        self.breaks.other();
+        self.out.push_str("\n\n");
+    }
+
+    fn story_template<F>(&mut self, story: F)
+    where
+        F: FnOnce(&mut Markdown),
+    {
+        if self.breaks.story() {
+            self.out.push_str("* * *\n\n");
+        }
+        story(self);
+    }
+
+    fn aside_template<F>(&mut self, _cls: &Option<&str>, aside: F)
+    where
+        F: FnOnce(&mut Markdown),
+    {
+        let aside = self.capture(aside);
+
+        for line in aside.trim_end().lines() {
+            self.out.push('>');
+            if !line.is_empty() {
+                self.out.push(' ');
+                self.out.push_str(line);
+            }
+            self.out.push('\n');
+        }
+        self.out.push('\n');
+        self.breaks.other();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Markdown;
+    use ogam::typography::ENGLISH;
+
+    fn compile_markdown(input: &str) -> String {
+        ogam::compile::<Markdown, _>(input, &ENGLISH)
+            .unwrap()
+            .into_string()
+    }
+
+    #[test]
+    fn markdown_keeps_spaces_out_of_emphasis() {
+        let res = compile_markdown("A *very* fine +day+, <I think.>");
+
+        assert!(res.contains("A *very* fine **day**, _I think._"));
+    }
+
+    #[test]
+    fn markdown_escapes_text() {
+        let res = compile_markdown("1984. Snake_case # [sic] & co.");
+
+        assert!(res.starts_with("1984\\. Snake\\_case \\# \\[sic\\] \\& co."));
+    }
+
+    #[test]
+    fn markdown_renders_sections() {
+        let res = compile_markdown(
+            "First story.\n\n===\n\nSecond story.\n\n____\n\nAn aside.\n\nStill aside.\n\n____",
+        );
+
+        assert_eq!(
+            res,
+            "First story.\n\n* * *\n\nSecond story.\n\n> An aside.\n>\n> Still aside.\n\n"
+        );
+    }
+}
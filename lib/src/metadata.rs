@@ -0,0 +1,71 @@
+use serde_derive::{Deserialize, Serialize};
+use uuid::Uuid;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum Identifier {
+    #[serde(rename = "isbn")]
+    Isbn(String),
+    #[serde(rename = "uuid")]
+    Uuid(String),
+}
+
+impl Identifier {
+    /// Derive an identifier from the author and the title of a book.  The
+    /// same book always gets the same identifier, so that e-readers
+    /// recognise two builds of a project as the same publication.
+    pub fn generate(author: &str, title: &str) -> Identifier {
+        let name = format!("celtchar:{}:{}", author, title);
+
+        Identifier::Uuid(Uuid::new_v5(&Uuid::NAMESPACE_URL, name.as_bytes()).to_string())
+    }
+
+    pub fn scheme(&self) -> &'static str {
+        match self {
+            Identifier::Isbn(_) => "ISBN",
+            Identifier::Uuid(_) => "UUID",
+        }
+    }
+
+    pub fn value(&self) -> &str {
+        match self {
+            Identifier::Isbn(ref x) => x,
+            Identifier::Uuid(ref x) => x,
+        }
+    }
+
+    pub fn urn(&self) -> String {
+        match self {
+            Identifier::Isbn(ref x) => format!("urn:isbn:{}", x),
+            Identifier::Uuid(ref x) => format!("urn:uuid:{}", x),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Series {
+    pub name: String,
+    pub index: Option<u32>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Contributor {
+    pub name: String,
+    /// A MARC relator code, *e.g.*, `edt` for an editor or `ill` for an
+    /// illustrator
+    pub role: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Metadata {
+    pub identifier: Option<Identifier>,
+    pub publisher: Option<String>,
+    pub date: Option<String>,
+    pub rights: Option<String>,
+    #[serde(default)]
+    pub subjects: Vec<String>,
+    pub series: Option<Series>,
+    #[serde(default)]
+    pub contributors: Vec<Contributor>,
+    /// The address where the static website of the book is published
+    pub url: Option<String>,
+}
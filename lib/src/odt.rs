@@ -0,0 +1,214 @@
+use ogam::generator::Output;
+use ogam::typography::Space;
+
+use crate::render::{escape_xml, StoryBreaks};
+
+/// The paragraphs of an OpenDocument text, using the paragraph styles
+/// `Story`, `Aside` and `SceneBreak`, and the character styles `Reply`,
+/// `Thought` and `Dialogue`.
+pub struct Odt {
+    out: String,
+    /// The style of the paragraphs being rendered
+    paragraph: &'static str,
+    /// Whether the last character written is a space, as consecutive
+    /// spaces are collapsed by OpenDocument
+    space: bool,
+    /// Whether nothing was written in the current paragraph yet
+    start: bool,
+    /// Whether we are rendering the lines of an ill-formed block
+    illformed_block: bool,
+    breaks: StoryBreaks,
+}
+
+impl Odt {
+    pub fn as_str(&self) -> &str {
+        &self.out
+    }
+
+    pub fn into_string(self) -> String {
+        self.out
+    }
+
+    fn open_paragraph(&mut self) {
+        self.out
+            .push_str(&format!("<text:p text:style-name=\"{}\">", self.paragraph));
+        self.start = true;
+        self.space = false;
+    }
+
+    fn close_paragraph(&mut self) {
+        self.out.push_str("</text:p>");
+    }
+
+    fn span<F>(&mut self, style: &str, format: F)
+    where
+        F: FnOnce(&mut Odt),
+    {
+        self.out
+            .push_str(&format!("<text:span text:style-name=\"{}\">", style));
+        format(self);
+        self.out.push_str("</text:span>");
+    }
+
+    fn push_text(&mut self, text: &str) {
+        escape_xml(text, &mut self.out);
+        self.start = false;
+        self.space = false;
+    }
+}
+
+impl Output for Odt {
+    fn empty(input_size: usize) -> Odt {
+        Odt {
+            out: String::with_capacity(2 * input_size),
+            paragraph: "Story",
+            space: false,
+            start: true,
+            illformed_block: false,
+            breaks: StoryBreaks::default(),
+        }
+    }
+
+    fn render_space(&mut self, space: Space) {
+        match space {
+            Space::Normal if self.start => (),
+            Space::Normal if self.space => self.out.push_str("<text:s/>"),
+            Space::Normal => {
+                self.out.push(' ');
+                self.space = true;
+            }
+            Space::Nbsp => self.push_text("\u{a0}"),
+            Space::None => (),
+        }
+    }
+
+    fn render_word(&mut self, word: &str) {
+        self.push_text(word)
+    }
+
+    fn render_mark(&mut self, mark: &str) {
+        self.push_text(mark)
+    }
+
+    fn render_illformed(&mut self, err: &str) {
+        // Each line of an ill-formed block but the first starts with a
+        // `text:line-break`
+        if self.illformed_block && !self.start {
+            self.out.push_str("<text:line-break/>");
+            self.space = false;
+        }
+        self.push_text(err)
+    }
+
+    fn emph_template<F>(&mut self, format: F)
+    where
+        F: FnOnce(&mut Odt),
+    {
+        self.span("Emphasis", format);
+    }
+
+    fn strong_emph_template<F>(&mut self, format: F)
+    where
+        F: FnOnce(&mut Odt),
+    {
+        self.span("Strong_20_Emphasis", format);
+    }
+
+    fn reply_template<F>(&mut self, reply: F, _author: &Option<&str>)
+    where
+        F: FnOnce(&mut Odt),
+    {
+        self.span("Reply", reply);
+    }
+
+    fn thought_template<F>(&mut self, reply: F, _author: &Option<&str>)
+    where
+        F: FnOnce(&mut Odt),
+    {
+        self.span("Thought", reply);
+    }
+
+    fn dialogue_template<F>(&mut self, reply: F, _author: &Option<&str>)
+    where
+        F: FnOnce(&mut Odt),
+    {
+        self.span("Dialogue", reply);
+    }
+
+    fn between_dialogue(&mut self) {
+        self.close_paragraph();
+        self.open_paragraph();
+    }
+
+    fn illformed_inline_template<F>(&mut self, err: F)
+    where
+        F: FnOnce(&mut Odt),
+    {
+        err(self);
+    }
+
+    fn paragraph_template<F>(&mut self, para: F)
+    where
+        F: FnOnce(&mut Odt),
+    {
+        self.open_paragraph();
+        para(self);
+        self.close_paragraph();
+    }
+
+    fn illformed_block_template<F>(&mut self, err: F)
+    where
+        F: FnOnce(&mut Odt),
+    {
+        self.open_paragraph();
+        self.illformed_block = true;
+        err(self);
+        self.illformed_block = false;
+        self.close_paragraph();
+        self.breaks.other();
+    }
+
+    fn story_template<F>(&mut self, story: F)
+    where
+        F: FnOnce(&mut Odt),
+    {
+        if self.breaks.story() {
+            self.out
+                .push_str("<text:p text:style-name=\"SceneBreak\">* * *</text:p>");
+        }
+        story(self);
+    }
+
+    fn aside_template<F>(&mut self, _cls: &Option<&str>, aside: F)
+    where
+        F: FnOnce(&mut Odt),
+    {
+        let previous = self.paragraph;
+        self.paragraph = "Aside";
+        aside(self);
+        self.paragraph = previous;
+        self.breaks.other();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Odt;
+    use ogam::typography::ENGLISH;
+
+    #[test]
+    fn odt_nests_spans() {
+        let res = ogam::compile::<Odt, _>("Fish & *chips* [Hi +there+.]", &ENGLISH)
+            .unwrap()
+            .into_string();
+
+        assert_eq!(
+            res,
+            "<text:p text:style-name=\"Story\">Fish &amp;\
+             <text:span text:style-name=\"Emphasis\"> chips</text:span>\
+             <text:span text:style-name=\"Dialogue\"> “<text:span text:style-name=\"Reply\">Hi\
+             <text:span text:style-name=\"Strong_20_Emphasis\"> there</text:span>.</text:span>”\
+             </text:span></text:p>"
+        );
+    }
+}
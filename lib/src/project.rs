@@ -1,8 +1,13 @@
 use ogam::generator::Output;
 use ogam::typography::{Typography, ENGLISH, FRENCH};
 use serde_derive::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 
+use crate::characters::Character;
+use crate::diagnostics::Diagnostic;
 use crate::error::{Error, Raise};
+use crate::fonts::FontFamily;
+use crate::metadata::{Identifier, Metadata};
 
 #[derive(Debug, Serialize, Deserialize)]
 pub enum Language {
@@ -19,12 +24,46 @@ impl Language {
     }
 }
 
+#[derive(Debug, Serialize, Deserialize)]
+pub enum EpubVersion {
+    #[serde(rename = "2")]
+    Epub2,
+    #[serde(rename = "3")]
+    Epub3,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Cover {
     pub extension: String,
     pub content: Vec<u8>,
 }
 
+impl Cover {
+    /// Guess the media type of the cover from its first bytes, regardless
+    /// of its extension.  Only the image formats e-readers are expected to
+    /// support are recognized.
+    pub fn media_type(&self) -> Result<&'static str, Error> {
+        let bytes = self.content.as_slice();
+
+        if bytes.starts_with(&[0xff, 0xd8, 0xff]) {
+            Ok("image/jpeg")
+        } else if bytes.starts_with(b"\x89PNG\r\n\x1a\n") {
+            Ok("image/png")
+        } else if bytes.starts_with(b"GIF87a") || bytes.starts_with(b"GIF89a") {
+            Ok("image/gif")
+        } else if bytes.len() >= 12 && bytes.starts_with(b"RIFF") && &bytes[8..12] == b"WEBP" {
+            Ok("image/webp")
+        } else if String::from_utf8_lossy(&bytes[..bytes.len().min(1024)]).contains("<svg") {
+            Ok("image/svg+xml")
+        } else {
+            Err(Error::new(&format!(
+                "unsupported cover format (.{}): expected a JPEG, PNG, GIF, SVG or WebP image",
+                self.extension
+            )))
+        }
+    }
+}
+
 pub trait Loader {
     type CovId;
     type DocId;
@@ -34,6 +73,10 @@ pub trait Loader {
 
     fn load_document(&self, id: &Self::DocId) -> Result<String, Error>;
 
+    /// A name for a document that readers can make sense of, to report
+    /// errors.
+    fn document_name(&self, id: &Self::DocId) -> String;
+
     fn load_project(&self, id: &Self::ProjId) -> Result<Project<Self::CovId, Self::DocId>, Error>;
 }
 
@@ -44,7 +87,12 @@ pub struct Chapter<I> {
 }
 
 impl<I> Chapter<I> {
-    fn load_and_render<T, L, O>(&self, loader: &L, typo: &T) -> Result<Chapter<O>, Error>
+    fn load_and_render<T, L, O>(
+        &self,
+        loader: &L,
+        typo: &T,
+        diagnostics: &mut Vec<Diagnostic>,
+    ) -> Result<Chapter<O>, Error>
     where
         T: Typography + ?Sized,
         L: Loader<DocId = I>,
@@ -55,10 +103,22 @@ impl<I> Chapter<I> {
 
         let doc = content
             .iter()
-            .map(|ref x| {
+            .map(|x| {
                 let input = loader.load_document(x)?;
-                ogam::compile(&input, typo)
-                    .or_raise("Cannot parse an ogmarkup document for some reason")
+                let name = loader.document_name(x);
+
+                let doc = ogam::parse(&input)
+                    .map_err(|err| Error::Parse(vec![Diagnostic::syntax(&name, &input, &err)]))?;
+
+                let mut found = Diagnostic::ill_formed(&name, &input, &doc);
+                found.append(&mut Diagnostic::unescaped_html(&name, &input));
+                found.sort_by_key(|d| (d.line, d.column));
+                diagnostics.append(&mut found);
+
+                let mut out = O::empty(input.len());
+                ogam::render(&doc, typo, &mut out);
+
+                Ok(out)
             })
             .collect::<Result<Vec<O>, Error>>()?;
 
@@ -77,7 +137,12 @@ pub struct Part<I> {
 }
 
 impl<I> Part<I> {
-    fn load_and_render<T, L, O>(&self, loader: &L, typo: &T) -> Result<Part<O>, Error>
+    fn load_and_render<T, L, O>(
+        &self,
+        loader: &L,
+        typo: &T,
+        diagnostics: &mut Vec<Diagnostic>,
+    ) -> Result<Part<O>, Error>
     where
         T: Typography + ?Sized,
         L: Loader<DocId = I>,
@@ -88,7 +153,7 @@ impl<I> Part<I> {
 
         let doc = content
             .iter()
-            .map(|ref chap| chap.load_and_render(loader, typo))
+            .map(|chap| chap.load_and_render(loader, typo, diagnostics))
             .collect::<Result<Vec<Chapter<O>>, Error>>()?;
 
         Ok(Part {
@@ -107,7 +172,14 @@ pub enum Content<I> {
 }
 
 impl<I> Content<I> {
-    fn load_and_render<T, L, O>(&self, loader: &L, typo: &T) -> Result<Content<O>, Error>
+    /// Load and render the documents of the book, and list the ill-formed
+    /// blocks and spans they contain.
+    pub fn load_and_render<T, L, O>(
+        &self,
+        loader: &L,
+        typo: &T,
+        diagnostics: &mut Vec<Diagnostic>,
+    ) -> Result<Content<O>, Error>
     where
         T: Typography + ?Sized,
         L: Loader<DocId = I>,
@@ -117,7 +189,7 @@ impl<I> Content<I> {
             Content::WithParts(ref parts) => {
                 let parts = parts
                     .iter()
-                    .map(|ref part| part.load_and_render(loader, typo))
+                    .map(|part| part.load_and_render(loader, typo, diagnostics))
                     .collect::<Result<Vec<Part<O>>, Error>>()?;
 
                 Ok(Content::WithParts(parts))
@@ -125,7 +197,7 @@ impl<I> Content<I> {
             Content::WithChapters(ref chapters) => {
                 let chapters = chapters
                     .iter()
-                    .map(|ref chap| chap.load_and_render(loader, typo))
+                    .map(|chap| chap.load_and_render(loader, typo, diagnostics))
                     .collect::<Result<Vec<Chapter<O>>, Error>>()?;
 
                 Ok(Content::WithChapters(chapters))
@@ -136,9 +208,7 @@ impl<I> Content<I> {
     pub fn chapters(&self) -> Vec<&Chapter<I>> {
         match self {
             Content::WithChapters(ref chaps) => chaps.iter().collect(),
-            Content::WithParts(ref parts) => {
-                parts.iter().map(|p| p.content.iter()).flatten().collect()
-            }
+            Content::WithParts(ref parts) => parts.iter().flat_map(|p| p.content.iter()).collect(),
         }
     }
 
@@ -147,8 +217,7 @@ impl<I> Content<I> {
             Content::WithChapters(ref mut chaps) => chaps.iter_mut().collect(),
             Content::WithParts(ref mut parts) => parts
                 .iter_mut()
-                .map(|p| p.content.iter_mut())
-                .flatten()
+                .flat_map(|p| p.content.iter_mut())
                 .collect(),
         }
     }
@@ -162,15 +231,48 @@ pub struct Project<C, I> {
     pub cover: Option<C>,
     pub numbering: Option<bool>,
     pub language: Language,
+    pub epub_version: Option<EpubVersion>,
+    pub metadata: Option<Metadata>,
+    /// The fonts to embed in the book.  ET Book is used when unspecified,
+    /// and an empty list means no font is embedded.
+    pub fonts: Option<Vec<FontFamily>>,
+    /// Reduce the embedded TTF and OTF fonts to the glyphs used by the book
+    pub subset_fonts: Option<bool>,
+    /// The characters of the book, by ogam author id
+    pub characters: Option<BTreeMap<String, Character>>,
+    /// Style the replies of the characters in the EPUB too, and not only
+    /// on the static website
+    pub epub_character_styles: Option<bool>,
     #[serde(flatten)]
     pub content: Content<I>,
 }
 
+impl<C, I> Project<C, I> {
+    /// The identifier of the book, as set in its metadata or generated
+    /// from its author and title otherwise.
+    pub fn identifier(&self) -> Identifier {
+        self.metadata
+            .as_ref()
+            .and_then(|m| m.identifier.clone())
+            .unwrap_or_else(|| Identifier::generate(&self.author, &self.title))
+    }
+}
+
 impl<O> Project<Cover, O> {
-    pub fn load_and_render<'input, L>(
+    pub fn load_and_render<L>(id: &L::ProjId, loader: &L) -> Result<Project<Cover, O>, Error>
+    where
+        L: Loader,
+        O: Output,
+    {
+        Project::load_and_render_with_diagnostics(id, loader).map(|(project, _)| project)
+    }
+
+    /// Load and render a project, and list the ill-formed blocks and spans
+    /// of its documents, which ogam renders as is.
+    pub fn load_and_render_with_diagnostics<L>(
         id: &L::ProjId,
         loader: &L,
-    ) -> Result<Project<Cover, O>, Error>
+    ) -> Result<(Project<Cover, O>, Vec<Diagnostic>), Error>
     where
         L: Loader,
         O: Output,
@@ -180,6 +282,12 @@ impl<O> Project<Cover, O> {
         let lang = project.language;
         let typo = lang.typography();
         let numbering = project.numbering;
+        let epub_version = project.epub_version;
+        let metadata = project.metadata;
+        let fonts = project.fonts;
+        let subset_fonts = project.subset_fonts;
+        let characters = project.characters;
+        let epub_character_styles = project.epub_character_styles;
         let descr = project.description;
         let author = project.author;
         let title = project.title;
@@ -188,9 +296,16 @@ impl<O> Project<Cover, O> {
             .map(|x| loader.load_cover(&x).or_raise("cannot load the cover"))
             .map_or(Ok(None), |r| r.map(Some))?;
 
-        let content = project.content.load_and_render(loader, typo)?;
+        if let Some(ref cover) = cover {
+            cover.media_type()?;
+        }
 
-        Ok(Project {
+        let mut diagnostics = vec![];
+        let content = project
+            .content
+            .load_and_render(loader, typo, &mut diagnostics)?;
+
+        let project = Project {
             author,
             title,
             description: descr,
@@ -198,6 +313,14 @@ impl<O> Project<Cover, O> {
             cover,
             numbering,
             language: lang,
-        })
+            epub_version,
+            metadata,
+            fonts,
+            subset_fonts,
+            characters,
+            epub_character_styles,
+        };
+
+        Ok((project, diagnostics))
     }
 }
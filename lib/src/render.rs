@@ -5,11 +5,46 @@ use ogam::typography::Space;
 pub struct Html(String);
 
 impl Html {
-    fn push_str(&mut self, s: &str) -> () {
+    fn push_str(&mut self, s: &str) {
         self.0.push_str(s);
     }
 
-    pub fn to_string(self) -> String {
+    /// Push text, escaping the characters with a special meaning in XHTML.
+    fn push_text(&mut self, s: &str) {
+        for c in s.chars() {
+            match c {
+                '&' => self.push_str("&amp;"),
+                '<' => self.push_str("&lt;"),
+                '>' => self.push_str("&gt;"),
+                '"' => self.push_str("&quot;"),
+                c => self.0.push(c),
+            }
+        }
+    }
+
+    /// Push `name` as a valid CSS identifier, usable as a class name.
+    fn push_class(&mut self, prefix: &str, name: &str) {
+        self.push_str(" ");
+        self.push_str(prefix);
+
+        if prefix.is_empty() && name.starts_with(|c: char| c.is_ascii_digit() || c == '-') {
+            self.push_str("_");
+        }
+
+        for c in name.chars() {
+            if c.is_ascii_alphanumeric() || c == '-' || c == '_' || !c.is_ascii() {
+                self.0.push(c);
+            } else {
+                self.0.push('_');
+            }
+        }
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    pub fn into_string(self) -> String {
         self.0
     }
 }
@@ -19,132 +54,222 @@ impl Output for Html {
         Html(String::with_capacity((15 * input_size) / 10))
     }
 
-    fn render_space(&mut self, space: Space) -> () {
+    fn render_space(&mut self, space: Space) {
         self.push_str(match space {
             Space::Normal => " ",
-            Space::Nbsp => "&nbsp;",
+            Space::Nbsp => "&#160;",
             Space::None => "",
         })
     }
 
-    fn render_word(&mut self, word: &str) -> () {
-        self.push_str(word)
+    fn render_word(&mut self, word: &str) {
+        self.push_text(word)
     }
 
-    fn render_mark(&mut self, mark: &str) -> () {
-        self.push_str(mark)
+    fn render_mark(&mut self, mark: &str) {
+        self.push_text(mark)
     }
 
-    fn render_illformed(&mut self, err: &str) -> () {
-        self.push_str(err)
+    fn render_illformed(&mut self, err: &str) {
+        self.push_text(err)
     }
 
-    fn emph_template<F>(&mut self, format: F) -> ()
+    fn emph_template<F>(&mut self, format: F)
     where
-        F: FnOnce(&mut Html) -> (),
+        F: FnOnce(&mut Html),
     {
         self.push_str("<em>");
         format(self);
         self.push_str("</em>");
     }
 
-    fn strong_emph_template<F>(&mut self, format: F) -> ()
+    fn strong_emph_template<F>(&mut self, format: F)
     where
-        F: FnOnce(&mut Html) -> (),
+        F: FnOnce(&mut Html),
     {
         self.push_str("<strong>");
         format(self);
         self.push_str("</strong>");
     }
 
-    fn reply_template<F>(&mut self, reply: F, _author: &Option<&str>) -> ()
+    fn reply_template<F>(&mut self, reply: F, author: &Option<&str>)
     where
-        F: FnOnce(&mut Html) -> (),
+        F: FnOnce(&mut Html),
     {
-        self.push_str("<span class=\"reply\">");
+        self.push_str("<span class=\"reply\"");
+        if let Some(author) = author {
+            self.push_str(" data-character=\"");
+            self.push_text(author);
+            self.push_str("\"");
+        }
+        self.push_str(">");
         reply(self);
         self.push_str("</span>");
     }
 
-    fn thought_template<F>(&mut self, reply: F, author: &Option<&str>) -> ()
+    fn thought_template<F>(&mut self, reply: F, author: &Option<&str>)
     where
-        F: FnOnce(&mut Html) -> (),
+        F: FnOnce(&mut Html),
     {
         self.push_str("<span class=\"thought");
-        author.map(|a| {
-            self.push_str(" by-");
-            self.push_str(a);
-        });
+        if let Some(author) = author {
+            self.push_class("by-", author);
+        }
         self.push_str("\">");
         reply(self);
         self.push_str("</span>");
     }
 
-    fn dialogue_template<F>(&mut self, reply: F, author: &Option<&str>) -> ()
+    fn dialogue_template<F>(&mut self, reply: F, author: &Option<&str>)
     where
-        F: FnOnce(&mut Html) -> (),
+        F: FnOnce(&mut Html),
     {
         self.push_str("<span class=\"dialogue");
-        author.map(|a| {
-            self.push_str(" by-");
-            self.push_str(a);
-        });
+        if let Some(author) = author {
+            self.push_class("by-", author);
+        }
         self.push_str("\">");
         reply(self);
         self.push_str("</span>");
     }
 
-    fn between_dialogue(&mut self) -> () {
+    fn between_dialogue(&mut self) {
         self.push_str("</p><p>");
     }
 
-    fn illformed_inline_template<F>(&mut self, err: F) -> ()
+    fn illformed_inline_template<F>(&mut self, err: F)
     where
-        F: FnOnce(&mut Html) -> (),
+        F: FnOnce(&mut Html),
     {
         self.push_str("<span class=\"illformed_inline\">");
         err(self);
         self.push_str("</span>");
     }
 
-    fn paragraph_template<F>(&mut self, para: F) -> ()
+    fn paragraph_template<F>(&mut self, para: F)
     where
-        F: FnOnce(&mut Html) -> (),
+        F: FnOnce(&mut Html),
     {
         self.push_str("<p>");
         para(self);
         self.push_str("</p>");
     }
 
-    fn illformed_block_template<F>(&mut self, err: F) -> ()
+    fn illformed_block_template<F>(&mut self, err: F)
     where
-        F: FnOnce(&mut Html) -> (),
+        F: FnOnce(&mut Html),
     {
         self.push_str("<div class=\"illformed_block\">");
         err(self);
         self.push_str("</div>");
     }
 
-    fn story_template<F>(&mut self, story: F) -> ()
+    fn story_template<F>(&mut self, story: F)
     where
-        F: FnOnce(&mut Html) -> (),
+        F: FnOnce(&mut Html),
     {
         self.push_str("<div class=\"story\">");
         story(self);
         self.push_str("</div>");
     }
 
-    fn aside_template<F>(&mut self, cls: &Option<&str>, aside: F) -> ()
+    fn aside_template<F>(&mut self, cls: &Option<&str>, aside: F)
     where
-        F: FnOnce(&mut Html) -> (),
+        F: FnOnce(&mut Html),
     {
         self.push_str("<div class=\"aside");
-        cls.map(|c| {
-            self.push_str(" ");
-            self.push_str(c);
-        });
+        if let Some(cls) = cls {
+            self.push_class("", cls);
+        }
         self.push_str("\">");
         aside(self);
         self.push_str("</div>");
     }
 }
+
+/// Track the sections of a document, to separate two consecutive stories
+/// with a break.  Asides and ill-formed blocks are not separated from the
+/// sections around them.
+#[derive(Default)]
+pub struct StoryBreaks {
+    after_story: bool,
+}
+
+impl StoryBreaks {
+    /// Tell whether the story about to be rendered follows another one.
+    pub fn story(&mut self) -> bool {
+        std::mem::replace(&mut self.after_story, true)
+    }
+
+    /// Note that a section which is not a story was rendered.
+    pub fn other(&mut self) {
+        self.after_story = false;
+    }
+}
+
+/// Escape the characters of `text` with a special meaning in XML.
+pub fn escape_xml(text: &str, out: &mut String) {
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            c => out.push(c),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Html;
+    use ogam::typography::ENGLISH;
+
+    fn compile(input: &str) -> String {
+        ogam::compile::<Html, _>(input, &ENGLISH)
+            .unwrap()
+            .into_string()
+    }
+
+    #[test]
+    fn escapes_text() {
+        let res = compile("Tom & Jerry & co.");
+
+        assert!(res.contains("Tom &amp; Jerry &amp; co."));
+    }
+
+    #[test]
+    fn escapes_illformed_content() {
+        let res = compile("It costs \"1 < 2\" pennies.");
+
+        assert!(res.contains("<span class=\"illformed_inline\">"));
+        assert!(res.contains("&quot;1 &lt; 2&quot; pennies."));
+
+        let res = compile("_____letter____\n</div><script>&amp;");
+
+        assert!(res.contains("<div class=\"illformed_block\">"));
+        assert!(!res.contains("</div><"));
+        assert!(!res.contains("<script>"));
+        assert!(res.contains("&amp;amp;"));
+    }
+
+    #[test]
+    fn escapes_markup() {
+        let res = compile("A <script>alert('x')</script> here.");
+
+        assert!(!res.contains("<script>"));
+        assert!(!res.contains("</script>"));
+    }
+
+    #[test]
+    fn sanitizes_class_names() {
+        let res = compile("[Hi there.](Anna) <So it begins.>(2nd)");
+
+        assert!(res.contains("class=\"dialogue by-Anna\""));
+        assert!(res.contains("<span class=\"reply\" data-character=\"Anna\">"));
+        assert!(res.contains("class=\"thought by-2nd\""));
+
+        let res = compile("____1st____\n\nA letter.\n\n_____________");
+
+        assert!(res.contains("class=\"aside _1st\""));
+    }
+}
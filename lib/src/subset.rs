@@ -0,0 +1,193 @@
+use std::collections::BTreeSet;
+
+use allsorts::binary::read::ReadScope;
+use allsorts::font::MatchingPresentation;
+use allsorts::font_data::FontData;
+use allsorts::subset::{subset, CmapTarget, SubsetProfile};
+use allsorts::Font;
+
+use crate::error::{Error, Raise};
+use crate::project::{Cover, Project};
+use crate::render::Html;
+
+fn decode_entity(entity: &str) -> Option<char> {
+    match entity {
+        "nbsp" => Some('\u{a0}'),
+        "amp" => Some('&'),
+        "lt" => Some('<'),
+        "gt" => Some('>'),
+        "quot" => Some('"'),
+        "apos" => Some('\''),
+        _ if entity.starts_with("#x") || entity.starts_with("#X") => {
+            u32::from_str_radix(&entity[2..], 16)
+                .ok()
+                .and_then(std::char::from_u32)
+        }
+        _ if entity.starts_with('#') => entity[1..].parse().ok().and_then(std::char::from_u32),
+        _ => None,
+    }
+}
+
+/// Collect the characters of the text nodes of an HTML fragment.
+fn collect_html(html: &str, acc: &mut BTreeSet<char>) {
+    let mut chars = html.chars();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '<' => {
+                chars.by_ref().find(|&c| c == '>');
+            }
+            '&' => {
+                let entity: String = chars.by_ref().take_while(|&c| c != ';').collect();
+                if let Some(c) = decode_entity(&entity) {
+                    acc.insert(c);
+                }
+            }
+            c => {
+                acc.insert(c);
+            }
+        }
+    }
+}
+
+fn collect_title(title: &Option<String>, acc: &mut BTreeSet<char>) {
+    if let Some(ref title) = title {
+        // Templates may display titles in upper case
+        acc.extend(
+            title
+                .chars()
+                .flat_map(|c| c.to_lowercase().chain(c.to_uppercase())),
+        );
+    }
+}
+
+/// The characters a book may be rendered with.  Besides its content and its
+/// metadata, this includes the printable ASCII characters, which are cheap
+/// to keep and cover the numbering and the labels added by the templates.
+pub fn used_chars(project: &Project<Cover, Html>) -> BTreeSet<char> {
+    let mut acc: BTreeSet<char> = (' '..='~').collect();
+
+    acc.extend("–—’«»“”…\u{a0}".chars());
+    collect_title(&Some(project.title.clone()), &mut acc);
+    collect_title(&Some(project.author.clone()), &mut acc);
+
+    if let Some(ref description) = project.description {
+        collect_html(description, &mut acc);
+    }
+
+    if let Some(ref metadata) = project.metadata {
+        let texts = metadata
+            .publisher
+            .iter()
+            .chain(&metadata.date)
+            .chain(&metadata.rights)
+            .chain(&metadata.subjects)
+            .chain(metadata.series.iter().map(|s| &s.name))
+            .chain(metadata.contributors.iter().map(|c| &c.name));
+
+        for text in texts {
+            acc.extend(text.chars());
+        }
+    }
+
+    if let crate::Content::WithParts(ref parts) = project.content {
+        for part in parts {
+            collect_title(&part.title, &mut acc);
+        }
+    }
+
+    for chapter in project.content.chapters() {
+        collect_title(&chapter.title, &mut acc);
+
+        for doc in &chapter.content {
+            collect_html(doc.as_str(), &mut acc);
+        }
+    }
+
+    acc
+}
+
+/// Reduce a TrueType or OpenType font to the glyphs needed to render
+/// `chars`.
+pub fn subset_font(data: &[u8], chars: &BTreeSet<char>) -> Result<Vec<u8>, Error> {
+    let font_data = ReadScope::new(data)
+        .read::<FontData<'_>>()
+        .or_raise("Could not parse font file")?;
+    let provider = font_data
+        .table_provider(0)
+        .or_raise("Could not read font tables")?;
+    let mut font = Font::new(provider).or_raise("Could not load font")?;
+
+    // The glyph 0 (.notdef) has to come first
+    let mut glyphs: Vec<u16> = vec![0];
+
+    for c in chars {
+        let (glyph, _) = font.lookup_glyph_index(*c, MatchingPresentation::NotRequired, None);
+
+        if glyph != 0 && !glyphs.contains(&glyph) {
+            glyphs.push(glyph);
+        }
+    }
+
+    subset(
+        &font.font_table_provider,
+        &glyphs,
+        &SubsetProfile::Minimal,
+        CmapTarget::Unicode,
+    )
+    .or_raise("Could not subset font")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{subset_font, used_chars};
+    use crate::assets::{Assets, Embedded};
+    use crate::project::{Chapter, Content, Language, Project};
+    use crate::render::Html;
+    use allsorts::binary::read::ReadScope;
+    use allsorts::font::MatchingPresentation;
+    use allsorts::font_data::FontData;
+    use allsorts::Font;
+    use ogam::typography::ENGLISH;
+    use std::path::Path;
+
+    fn has_glyph(data: &[u8], c: char) -> bool {
+        let font_data = ReadScope::new(data).read::<FontData<'_>>().unwrap();
+        let mut font = Font::new(font_data.table_provider(0).unwrap()).unwrap();
+        let (glyph, _) = font.lookup_glyph_index(c, MatchingPresentation::NotRequired, None);
+
+        glyph != 0
+    }
+
+    #[test]
+    fn keeps_the_glyphs_of_the_author() {
+        let project: Project<_, Html> = Project {
+            author: String::from("Zoë"),
+            title: String::from("Tales"),
+            description: None,
+            cover: None,
+            numbering: None,
+            language: Language::En,
+            epub_version: None,
+            metadata: None,
+            fonts: None,
+            subset_fonts: Some(true),
+            characters: None,
+            epub_character_styles: None,
+            content: Content::WithChapters(vec![Chapter {
+                title: None,
+                content: vec![ogam::compile("Once upon a time.", &ENGLISH).unwrap()],
+            }]),
+        };
+
+        let font = Embedded
+            .font(Path::new("et-book-roman-line-figures.ttf"))
+            .unwrap();
+        assert!(has_glyph(&font, 'ë'));
+
+        let subset = subset_font(&font, &used_chars(&project)).unwrap();
+
+        assert!(has_glyph(&subset, 'ë'));
+        assert!(!has_glyph(&subset, 'ŵ'));
+    }
+}
@@ -0,0 +1,146 @@
+use ogam::generator::Output;
+use ogam::typography::Space;
+
+use crate::render::StoryBreaks;
+
+/// Plain UTF-8 text, with one line per paragraph and paragraphs separated
+/// by a blank line.  Asides are indented.
+pub struct Text {
+    out: String,
+    breaks: StoryBreaks,
+}
+
+impl Text {
+    pub fn as_str(&self) -> &str {
+        &self.out
+    }
+
+    pub fn into_string(self) -> String {
+        self.out
+    }
+}
+
+impl Output for Text {
+    fn empty(input_size: usize) -> Text {
+        Text {
+            out: String::with_capacity(input_size),
+            breaks: StoryBreaks::default(),
+        }
+    }
+
+    fn render_space(&mut self, space: Space) {
+        self.out.push_str(match space {
+            Space::Normal => " ",
+            Space::Nbsp => "\u{a0}",
+            Space::None => "",
+        })
+    }
+
+    fn render_word(&mut self, word: &str) {
+        self.out.push_str(word)
+    }
+
+    fn render_mark(&mut self, mark: &str) {
+        self.out.push_str(mark)
+    }
+
+    fn render_illformed(&mut self, err: &str) {
+        self.out.push_str(err)
+    }
+
+    fn emph_template<F>(&mut self, format: F)
+    where
+        F: FnOnce(&mut Text),
+    {
+        format(self);
+    }
+
+    fn strong_emph_template<F>(&mut self, format: F)
+    where
+        F: FnOnce(&mut Text),
+    {
+        format(self);
+    }
+
+    fn reply_template<F>(&mut self, reply: F, _author: &Option<&str>)
+    where
+        F: FnOnce(&mut Text),
+    {
+        reply(self);
+    }
+
+    fn thought_template<F>(&mut self, reply: F, _author: &Option<&str>)
+    where
+        F: FnOnce(&mut Text),
+    {
+        reply(self);
+    }
+
+    fn dialogue_template<F>(&mut self, reply: F, _author: &Option<&str>)
+    where
+        F: FnOnce(&mut Text),
+    {
+        reply(self);
+    }
+
+    fn between_dialogue(&mut self) {
+        self.out.push_str("\n\n");
+    }
+
+    fn illformed_inline_template<F>(&mut self, err: F)
+    where
+        F: FnOnce(&mut Text),
+    {
+        err(self);
+    }
+
+    fn paragraph_template<F>(&mut self, para: F)
+    where
+        F: FnOnce(&mut Text),
+    {
+        let start = self.out.len();
+        para(self);
+        let para = self.out.split_off(start);
+
+        self.out.push_str(para.trim());
+        self.out.push_str("\n\n");
+    }
+
+    fn illformed_block_template<F>(&mut self, err: F)
+    where
+        F: FnOnce(&mut Text),
+    {
+        err(self);
+        self.breaks.other();
+        self.out.push_str("\n\n");
+    }
+
+    fn story_template<F>(&mut self, story: F)
+    where
+        F: FnOnce(&mut Text),
+    {
+        if self.breaks.story() {
+            self.out.push_str("* * *\n\n");
+        }
+        story(self);
+    }
+
+    fn aside_template<F>(&mut self, _cls: &Option<&str>, aside: F)
+    where
+        F: FnOnce(&mut Text),
+    {
+        let start = self.out.len();
+        aside(self);
+        let aside = self.out.split_off(start);
+
+        for line in aside.trim_end().lines() {
+            if !line.is_empty() {
+                self.out.push_str("    ");
+                self.out.push_str(line);
+            }
+            self.out.push('\n');
+        }
+        self.out.push('\n');
+        self.breaks.other();
+    }
+}
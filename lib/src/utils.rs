@@ -0,0 +1,42 @@
+use std::collections::HashMap;
+use tera::{Error as TError, Result as TResult, Value};
+
+/// Write `value` in roman numerals, for the templates to number parts and
+/// chapters.
+pub(crate) fn roman_filter(value: &Value, _kargs: &HashMap<String, Value>) -> TResult<Value> {
+    let result = roman::to(
+        value
+            .as_i64()
+            .map(|x| x as i32)
+            .ok_or(TError::msg("Expected integer"))?,
+    )
+    .ok_or(TError::msg("Could not convert to roman notation"))?;
+
+    Ok(Value::String(result))
+}
+
+/// Encode `data` in base64, with padding.
+pub(crate) fn base64(data: &[u8]) -> String {
+    const ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+    let mut res = String::with_capacity(data.len().div_ceil(3) * 4);
+
+    for chunk in data.chunks(3) {
+        let bytes = [
+            chunk[0],
+            *chunk.get(1).unwrap_or(&0),
+            *chunk.get(2).unwrap_or(&0),
+        ];
+        let n = (u32::from(bytes[0]) << 16) | (u32::from(bytes[1]) << 8) | u32::from(bytes[2]);
+
+        for i in 0..4 {
+            if i <= chunk.len() {
+                res.push(ALPHABET[(n >> (18 - 6 * i) & 0x3f) as usize] as char);
+            } else {
+                res.push('=');
+            }
+        }
+    }
+
+    res
+}
@@ -0,0 +1,457 @@
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::io::{Read, Seek};
+
+use percent_encoding::percent_decode_str;
+use roxmltree::{Document, ParsingOptions};
+use serde_derive::Serialize;
+use zip::{CompressionMethod, ZipArchive};
+
+use crate::error::{Error, Raise};
+
+/// A requirement of the EPUB specification an archive does not meet.
+#[derive(Debug, Serialize)]
+pub struct Violation {
+    /// The entry of the archive at fault, if any
+    pub file: Option<String>,
+    pub message: String,
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.file {
+            Some(ref file) => write!(f, "{}: {}", file, self.message),
+            None => write!(f, "{}", self.message),
+        }
+    }
+}
+
+struct Validator<R: Read + Seek> {
+    archive: ZipArchive<R>,
+    files: HashSet<String>,
+    violations: Vec<Violation>,
+}
+
+/// Resolve `href`, found in the entry `base` of the archive, to the name of
+/// an entry.  Fragments are dropped, and percent-encoded characters are
+/// decoded.
+fn resolve(base: &str, href: &str) -> String {
+    let href = href.split('#').next().unwrap_or("");
+    let mut parts: Vec<Cow<str>> = base.split('/').map(Cow::from).collect();
+    parts.pop();
+
+    for part in href.split('/') {
+        match part {
+            "" | "." => (),
+            ".." => {
+                parts.pop();
+            }
+            part => parts.push(percent_decode_str(part).decode_utf8_lossy()),
+        }
+    }
+
+    parts.join("/")
+}
+
+fn parse_xml(input: &str) -> Result<Document<'_>, roxmltree::Error> {
+    let options = ParsingOptions {
+        allow_dtd: true,
+        ..ParsingOptions::default()
+    };
+
+    Document::parse_with_options(input, options)
+}
+
+impl<R: Read + Seek> Validator<R> {
+    fn violation(&mut self, file: Option<&str>, message: String) {
+        self.violations.push(Violation {
+            file: file.map(String::from),
+            message,
+        });
+    }
+
+    fn read(&mut self, name: &str) -> Option<String> {
+        let mut content = String::new();
+
+        let res = match self.archive.by_name(name) {
+            Ok(mut file) => file
+                .read_to_string(&mut content)
+                .map_err(|err| format!("cannot be read: {}", err)),
+            Err(_) => Err(String::from("missing from the archive")),
+        };
+
+        match res {
+            Ok(_) => Some(content),
+            Err(message) => {
+                self.violation(Some(name), message);
+                None
+            }
+        }
+    }
+
+    fn check_mimetype(&mut self) -> Result<(), Error> {
+        let mut first = self.archive.by_index(0).or_raise("The archive is empty")?;
+
+        let name = String::from(first.name());
+        let stored = first.compression() == CompressionMethod::Stored;
+        let mut content = String::new();
+        let readable = first.read_to_string(&mut content).is_ok();
+
+        drop(first);
+
+        if name != "mimetype" {
+            self.violation(
+                Some(&name),
+                String::from("the first entry of the archive must be mimetype"),
+            );
+        } else {
+            if !stored {
+                self.violation(
+                    Some("mimetype"),
+                    String::from("must be stored uncompressed"),
+                );
+            }
+
+            if !readable || content != "application/epub+zip" {
+                self.violation(
+                    Some("mimetype"),
+                    String::from("must only contain application/epub+zip"),
+                );
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Find the path of the package document in the container.
+    fn rootfile(&mut self) -> Option<String> {
+        let container = "META-INF/container.xml";
+        let input = self.read(container)?;
+
+        let res = match parse_xml(&input) {
+            Ok(doc) => doc
+                .descendants()
+                .find(|n| n.has_tag_name("rootfile"))
+                .and_then(|n| n.attribute("full-path"))
+                .map(String::from),
+            Err(err) => {
+                self.violation(Some(container), format!("ill-formed XML: {}", err));
+                return None;
+            }
+        };
+
+        if res.is_none() {
+            self.violation(Some(container), String::from("no rootfile is declared"));
+        }
+
+        res
+    }
+
+    /// Check an XML document is well-formed, and give back the targets of
+    /// the links found in the elements `tag` with attribute `attr`.
+    fn check_xml(&mut self, name: &str, tag: &str, attr: &str) -> Vec<String> {
+        let input = match self.read(name) {
+            Some(input) => input,
+            None => return vec![],
+        };
+
+        match parse_xml(&input) {
+            Ok(doc) => doc
+                .descendants()
+                .filter(|n| n.has_tag_name(tag))
+                .filter_map(|n| n.attribute(attr))
+                .filter(|href| !href.contains("://") && !href.starts_with('#'))
+                .map(|href| resolve(name, href))
+                .collect(),
+            Err(err) => {
+                self.violation(Some(name), format!("ill-formed XML: {}", err));
+                vec![]
+            }
+        }
+    }
+
+    fn check_targets(&mut self, name: &str, targets: Vec<String>) {
+        for target in targets {
+            if !self.files.contains(&target) {
+                self.violation(
+                    Some(name),
+                    format!("links to {}, which does not exist", target),
+                );
+            }
+        }
+    }
+
+    fn check_package(&mut self, opf: &str) {
+        let input = match self.read(opf) {
+            Some(input) => input,
+            None => return,
+        };
+
+        let doc = match parse_xml(&input) {
+            Ok(doc) => doc,
+            Err(err) => {
+                self.violation(Some(opf), format!("ill-formed XML: {}", err));
+                return;
+            }
+        };
+
+        let mut manifest = HashMap::new();
+
+        for item in doc.descendants().filter(|n| n.has_tag_name("item")) {
+            let (id, href) = match (item.attribute("id"), item.attribute("href")) {
+                (Some(id), Some(href)) => (id, href),
+                _ => {
+                    self.violation(
+                        Some(opf),
+                        String::from("a manifest item lacks an id or an href"),
+                    );
+                    continue;
+                }
+            };
+
+            let path = resolve(opf, href);
+
+            if !self.files.contains(&path) {
+                self.violation(
+                    Some(opf),
+                    format!(
+                        "manifest item {} refers to {}, which does not exist",
+                        id, path
+                    ),
+                );
+            }
+
+            let media_type = item.attribute("media-type").unwrap_or("");
+            let is_nav = item
+                .attribute("properties")
+                .is_some_and(|p| p.split_whitespace().any(|p| p == "nav"));
+
+            manifest.insert(id, (path, media_type, is_nav));
+        }
+
+        let spine = doc.descendants().find(|n| n.has_tag_name("spine"));
+
+        if let Some(toc) = spine.and_then(|s| s.attribute("toc")) {
+            if !manifest.contains_key(toc) {
+                self.violation(
+                    Some(opf),
+                    format!("spine toc {} is not in the manifest", toc),
+                );
+            }
+        }
+
+        for itemref in doc.descendants().filter(|n| n.has_tag_name("itemref")) {
+            match itemref.attribute("idref") {
+                Some(idref) if manifest.contains_key(idref) => (),
+                Some(idref) => self.violation(
+                    Some(opf),
+                    format!("spine item {} is not in the manifest", idref),
+                ),
+                None => self.violation(Some(opf), String::from("a spine item lacks an idref")),
+            }
+        }
+
+        let mut items: Vec<_> = manifest.into_values().collect();
+        items.sort();
+
+        for (path, media_type, is_nav) in items {
+            if !self.files.contains(&path) {
+                continue;
+            }
+
+            match media_type {
+                "application/xhtml+xml" => {
+                    let targets = self.check_xml(&path, "a", "href");
+
+                    if is_nav {
+                        self.check_targets(&path, targets);
+                    }
+                }
+                "application/x-dtbncx+xml" => {
+                    let targets = self.check_xml(&path, "content", "src");
+                    self.check_targets(&path, targets);
+                }
+                _ => (),
+            }
+        }
+    }
+}
+
+/// Check an EPUB archive against the parts of the specification e-readers
+/// are the least forgiving about.  An empty list means no issue was found.
+pub fn validate_epub<R: Read + Seek>(input: R) -> Result<Vec<Violation>, Error> {
+    let archive = ZipArchive::new(input).or_raise("Could not read the archive")?;
+    let files = archive.file_names().map(String::from).collect();
+
+    let mut validator = Validator {
+        archive,
+        files,
+        violations: vec![],
+    };
+
+    validator.check_mimetype()?;
+
+    if let Some(opf) = validator.rootfile() {
+        validator.check_package(&opf);
+    }
+
+    Ok(validator.violations)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::validate_epub;
+    use std::io::{Cursor, Write};
+    use zip::write::FileOptions;
+    use zip::{CompressionMethod, ZipWriter};
+
+    const CONTAINER: &str = r#"<?xml version="1.0"?>
+<container version="1.0" xmlns="urn:oasis:names:tc:opendocument:xmlns:container">
+  <rootfiles>
+    <rootfile full-path="OEBPS/content.opf" media-type="application/oebps-package+xml"/>
+  </rootfiles>
+</container>"#;
+
+    const PACKAGE: &str = r#"<?xml version="1.0"?>
+<package xmlns="http://www.idpf.org/2007/opf" version="2.0">
+  <manifest>
+    <item id="ncx" href="toc.ncx" media-type="application/x-dtbncx+xml"/>
+    <item id="ch1" href="Text/ch1.xhtml" media-type="application/xhtml+xml"/>
+    <item id="font" href="Fonts/my%20font.ttf" media-type="font/ttf"/>
+  </manifest>
+  <spine toc="ncx">
+    <itemref idref="ch1"/>
+  </spine>
+</package>"#;
+
+    const NCX: &str = r#"<?xml version="1.0"?>
+<ncx xmlns="http://www.daisy.org/z3986/2005/ncx/" version="2005-1">
+  <navMap>
+    <navPoint id="ch1"><content src="Text/ch1.xhtml#top"/></navPoint>
+  </navMap>
+</ncx>"#;
+
+    const CHAPTER: &str = r#"<?xml version="1.0"?>
+<html xmlns="http://www.w3.org/1999/xhtml"><body><p id="top">Once.</p></body></html>"#;
+
+    fn entries() -> Vec<(&'static str, String)> {
+        vec![
+            ("mimetype", String::from("application/epub+zip")),
+            ("META-INF/container.xml", String::from(CONTAINER)),
+            ("OEBPS/content.opf", String::from(PACKAGE)),
+            ("OEBPS/toc.ncx", String::from(NCX)),
+            ("OEBPS/Text/ch1.xhtml", String::from(CHAPTER)),
+            ("OEBPS/Fonts/my font.ttf", String::from("font")),
+        ]
+    }
+
+    /// Validate an archive of `entries`, where only `deflated` is
+    /// compressed, and describe the violations found.
+    fn validate(entries: &[(&str, String)], deflated: &str) -> Vec<String> {
+        let mut zip = ZipWriter::new(Cursor::new(vec![]));
+
+        for (name, content) in entries {
+            let method = if *name == deflated {
+                CompressionMethod::Deflated
+            } else {
+                CompressionMethod::Stored
+            };
+
+            zip.start_file(*name, FileOptions::default().compression_method(method))
+                .unwrap();
+            zip.write_all(content.as_bytes()).unwrap();
+        }
+
+        let output = zip.finish().unwrap();
+
+        validate_epub(output)
+            .unwrap()
+            .iter()
+            .map(|v| v.to_string())
+            .collect()
+    }
+
+    fn replace(entry: &str, from: &str, to: &str) -> Vec<(&'static str, String)> {
+        let mut entries = entries();
+
+        for (name, content) in entries.iter_mut() {
+            if *name == entry {
+                *content = content.replace(from, to);
+            }
+        }
+
+        entries
+    }
+
+    // The font of the package is referred to with a percent-encoded href
+    #[test]
+    fn accepts_a_valid_archive() {
+        assert!(validate(&entries(), "").is_empty());
+    }
+
+    #[test]
+    fn rejects_a_deflated_mimetype() {
+        assert_eq!(
+            validate(&entries(), "mimetype"),
+            vec!["mimetype: must be stored uncompressed"]
+        );
+    }
+
+    #[test]
+    fn rejects_a_mimetype_out_of_place() {
+        let mut entries = entries();
+        entries.swap(0, 1);
+
+        assert_eq!(
+            validate(&entries, ""),
+            vec!["META-INF/container.xml: the first entry of the archive must be mimetype"]
+        );
+    }
+
+    #[test]
+    fn rejects_a_spine_item_out_of_the_manifest() {
+        let entries = replace("OEBPS/content.opf", r#"idref="ch1""#, r#"idref="ch2""#);
+
+        assert_eq!(
+            validate(&entries, ""),
+            vec!["OEBPS/content.opf: spine item ch2 is not in the manifest"]
+        );
+    }
+
+    #[test]
+    fn rejects_a_manifest_item_out_of_the_archive() {
+        let mut entries = entries();
+        entries.retain(|(name, _)| *name != "OEBPS/Fonts/my font.ttf");
+
+        assert_eq!(
+            validate(&entries, ""),
+            vec![
+                "OEBPS/content.opf: manifest item font refers to OEBPS/Fonts/my font.ttf, which does not exist"
+            ]
+        );
+    }
+
+    #[test]
+    fn rejects_ill_formed_xhtml() {
+        let entries = replace("OEBPS/Text/ch1.xhtml", "</p>", "");
+        let violations = validate(&entries, "");
+
+        assert_eq!(violations.len(), 1);
+        assert!(
+            violations[0].starts_with("OEBPS/Text/ch1.xhtml: ill-formed XML"),
+            "{:?}",
+            violations
+        );
+    }
+
+    #[test]
+    fn rejects_a_dangling_table_of_contents() {
+        let entries = replace("OEBPS/toc.ncx", "ch1.xhtml", "ch2.xhtml");
+
+        assert_eq!(
+            validate(&entries, ""),
+            vec!["OEBPS/toc.ncx: links to OEBPS/Text/ch2.xhtml, which does not exist"]
+        );
+    }
+}
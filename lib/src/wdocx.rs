@@ -0,0 +1,106 @@
+use std::path::{Path, PathBuf};
+use tera::{Context, Tera};
+
+use crate::assets::{load_templates, Assets};
+use crate::docx::Docx;
+use crate::epub::{build_timestamp, format_timestamp};
+use crate::error::Error;
+use crate::project::{Cover, Project};
+use crate::utils::roman_filter;
+use crate::writer::{insert_contents, BookWriter};
+
+/// Render each chapter of the book with its heading, as WordprocessingML
+/// paragraphs.
+fn render_chapters(project: &Project<Cover, Docx>, tera: &Tera) -> Result<Vec<String>, Error> {
+    project
+        .content
+        .chapters()
+        .iter()
+        .enumerate()
+        .map(|(idx, chapter)| {
+            let content: Vec<&str> = chapter.content.iter().map(Docx::as_str).collect();
+
+            let mut ctx = Context::new();
+            ctx.insert("number", &(idx + 1));
+            ctx.insert("title", &chapter.title);
+            ctx.insert("content", &content);
+            ctx.insert("numbering", &project.numbering.unwrap_or(false));
+
+            tera.render("docx/chapter.xml", &ctx)
+                .map_err(|e| Error::template(Some("docx/chapter.xml"), e))
+        })
+        .collect()
+}
+
+pub trait DocxWriter: BookWriter {
+    fn create_package(&mut self, tera: &Tera) -> Result<(), Error> {
+        let ctx = Context::new();
+
+        self.write_template(
+            &PathBuf::from("[Content_Types].xml"),
+            tera,
+            "docx/content_types.xml",
+            &ctx,
+        )?;
+        self.write_template(&PathBuf::from("_rels/.rels"), tera, "docx/rels.xml", &ctx)?;
+        self.write_template(
+            &PathBuf::from("word/_rels/document.xml.rels"),
+            tera,
+            "docx/document.xml.rels",
+            &ctx,
+        )
+    }
+
+    /// Write an Office Open XML document of the book, to be opened with a
+    /// word processor.  Parts and chapters are given a page of their own,
+    /// and the text is styled with the `Dialogue`, `Thought`, `Aside` and
+    /// `SceneBreak` styles, for editors to adjust them.
+    fn generate_docx(
+        &mut self,
+        project: &Project<Cover, Docx>,
+        assets: &dyn Assets,
+        templates: Option<&Path>,
+    ) -> Result<(), Error> {
+        let mut tera = load_templates(assets, templates)?;
+
+        tera.register_filter("roman", roman_filter);
+
+        self.create_package(&tera)?;
+
+        let chapters = render_chapters(project, &tera)?;
+
+        let mut ctx = Context::new();
+
+        insert_contents(&mut ctx, &project.content, |idx, _| &chapters[idx]);
+
+        ctx.insert("title", &project.title);
+        ctx.insert("author", &project.author);
+        ctx.insert("description", &project.description);
+        ctx.insert("numbering", &project.numbering.unwrap_or(false));
+        ctx.insert("language", &project.language);
+        ctx.insert("modified", &format_timestamp(build_timestamp()?));
+
+        self.write_template(
+            &PathBuf::from("word/document.xml"),
+            &tera,
+            "docx/document.xml",
+            &ctx,
+        )?;
+        self.write_template(
+            &PathBuf::from("word/styles.xml"),
+            &tera,
+            "docx/styles.xml",
+            &ctx,
+        )?;
+        self.write_template(
+            &PathBuf::from("docProps/core.xml"),
+            &tera,
+            "docx/core.xml",
+            &ctx,
+        )?;
+
+        Ok(())
+    }
+}
+
+impl<W> DocxWriter for W where W: BookWriter {}
@@ -0,0 +1,138 @@
+use serde_json::{json, Value};
+use std::path::Path;
+use tera::{Context, Tera};
+
+use crate::assets::{load_templates, Assets};
+use crate::epub::{build_timestamp, format_timestamp};
+use crate::error::Error;
+use crate::fb2::Fb2;
+use crate::metadata::Identifier;
+use crate::project::{Cover, Project};
+use crate::utils::{base64, roman_filter};
+use crate::writer::{insert_contents, BookWriter, DirectoryWriter};
+
+/// Split a name in the first and last names expected by FictionBook.  A
+/// name of a single word has no last name, and is used as a nickname.
+fn person(name: &str) -> Value {
+    let words: Vec<&str> = name.split_whitespace().collect();
+
+    match words.split_last() {
+        Some((last, first)) if !first.is_empty() => json!({
+            "full_name": name,
+            "first_name": first.join(" "),
+            "last_name": last,
+        }),
+        _ => json!({
+            "full_name": name,
+            "first_name": name.trim(),
+            "last_name": null,
+        }),
+    }
+}
+
+pub struct Fb2Writer {
+    dir: DirectoryWriter,
+}
+
+impl Fb2Writer {
+    pub fn init(base: &Path) -> Result<Fb2Writer, Error> {
+        Ok(Fb2Writer {
+            dir: DirectoryWriter::init(base)?,
+        })
+    }
+
+    /// Render each chapter of the book as a section.
+    fn render_chapters(
+        &self,
+        project: &Project<Cover, Fb2>,
+        tera: &Tera,
+    ) -> Result<Vec<String>, Error> {
+        project
+            .content
+            .chapters()
+            .iter()
+            .enumerate()
+            .map(|(idx, chapter)| {
+                let content: Vec<&str> = chapter.content.iter().map(Fb2::as_str).collect();
+
+                let mut ctx = Context::new();
+                ctx.insert("number", &(idx + 1));
+                ctx.insert("title", &chapter.title);
+                ctx.insert("content", &content);
+                ctx.insert("numbering", &project.numbering.unwrap_or(false));
+
+                tera.render("fb2/chapter.fb2", &ctx)
+                    .map_err(|e| Error::template(Some("fb2/chapter.fb2"), e))
+            })
+            .collect()
+    }
+
+    /// Write the whole book in the FictionBook file `name`.  JPEG and PNG
+    /// covers are embedded in the file.
+    pub fn generate_fb2(
+        &mut self,
+        project: &Project<Cover, Fb2>,
+        assets: &dyn Assets,
+        templates: Option<&Path>,
+        name: &Path,
+    ) -> Result<(), Error> {
+        let mut tera = load_templates(assets, templates)?;
+
+        tera.register_filter("roman", roman_filter);
+
+        let chapters = self.render_chapters(project, &tera)?;
+
+        let mut ctx = Context::new();
+
+        insert_contents(&mut ctx, &project.content, |idx, _| &chapters[idx]);
+
+        let cover = match project.cover {
+            Some(ref cov) => match cov.media_type()? {
+                media_type @ ("image/jpeg" | "image/png") => Some(json!({
+                    "id": format!("cover.{}", cov.extension),
+                    "media_type": media_type,
+                    "data": base64(&cov.content),
+                })),
+                _ => None,
+            },
+            None => None,
+        };
+
+        let translators: Vec<Value> = project
+            .metadata
+            .iter()
+            .flat_map(|m| m.contributors.iter())
+            .filter(|c| c.role == "trl")
+            .map(|c| person(&c.name))
+            .collect();
+
+        let isbn = match project.identifier() {
+            Identifier::Isbn(isbn) => Some(isbn),
+            _ => None,
+        };
+
+        let year = project
+            .metadata
+            .as_ref()
+            .and_then(|m| m.date.as_ref())
+            .and_then(|date| date.get(..4))
+            .filter(|year| year.chars().all(|c| c.is_ascii_digit()));
+
+        let modified = format_timestamp(build_timestamp()?);
+
+        ctx.insert("title", &project.title);
+        ctx.insert("author", &person(&project.author));
+        ctx.insert("translators", &translators);
+        ctx.insert("description", &project.description);
+        ctx.insert("numbering", &project.numbering.unwrap_or(false));
+        ctx.insert("language", &project.language);
+        ctx.insert("metadata", &project.metadata);
+        ctx.insert("identifier", project.identifier().value());
+        ctx.insert("isbn", &isbn);
+        ctx.insert("year", &year);
+        ctx.insert("modified", &modified[..10]);
+        ctx.insert("cover", &cover);
+
+        self.dir.write_template(name, &tera, "fb2/book.fb2", &ctx)
+    }
+}
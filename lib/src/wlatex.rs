@@ -0,0 +1,175 @@
+use serde_json::{json, Value as JsonValue};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use tera::{Context, Error as TError, Result as TResult, Tera, Value};
+
+use crate::assets::{load_templates, Assets};
+use crate::error::Error;
+use crate::fonts::{FontFace, FontFamily};
+use crate::latex::{escape_latex, Latex};
+use crate::metadata::Identifier;
+use crate::project::{Chapter, Cover, Project};
+use crate::utils::roman_filter;
+use crate::writer::{insert_contents, BookWriter, DirectoryWriter};
+
+fn latex_filter(value: &Value, _kargs: &HashMap<String, Value>) -> TResult<Value> {
+    let text = value.as_str().ok_or(TError::msg("Expected string"))?;
+
+    Ok(Value::String(escape_latex(text)))
+}
+
+pub struct LatexWriter {
+    dir: DirectoryWriter,
+}
+
+fn is_bold(face: &FontFace) -> bool {
+    face.weight == "bold" || face.weight.parse::<u32>().is_ok_and(|w| w >= 600)
+}
+
+fn is_italic(face: &FontFace) -> bool {
+    face.style == "italic" || face.style == "oblique"
+}
+
+impl LatexWriter {
+    pub fn init(base: &Path) -> Result<LatexWriter, Error> {
+        Ok(LatexWriter {
+            dir: DirectoryWriter::init(base)?,
+        })
+    }
+
+    /// Copy the faces of the main font of the book in `fonts/`, and give
+    /// back their file names by role, for `fontspec`.  WOFF fonts are not
+    /// supported by LaTeX, and are left out.
+    fn install_font(
+        &mut self,
+        assets: &dyn Assets,
+        family: &FontFamily,
+    ) -> Result<Option<JsonValue>, Error> {
+        let mut roles = HashMap::new();
+
+        for face in &family.faces {
+            let extension = face
+                .file
+                .extension()
+                .and_then(|x| x.to_str())
+                .map(|x| x.to_lowercase());
+
+            if let Some("woff" | "woff2") = extension.as_deref() {
+                continue;
+            }
+
+            let role = match (is_bold(face), is_italic(face)) {
+                (false, false) => "upright",
+                (true, false) => "bold",
+                (false, true) => "italic",
+                (true, true) => "bold_italic",
+            };
+
+            let name = face.file_name()?;
+
+            self.dir
+                .write_bytes(&PathBuf::from("fonts").join(&name), &face.load(assets)?)?;
+            roles.insert(role, name);
+        }
+
+        if roles.contains_key("upright") {
+            Ok(Some(json!(roles)))
+        } else {
+            Ok(None)
+        }
+    }
+
+    fn generate_chapters(
+        &mut self,
+        tera: &Tera,
+        chapters: &[&Chapter<Latex>],
+    ) -> Result<(), Error> {
+        for (idx, chapter) in chapters.iter().enumerate() {
+            let content: Vec<&str> = chapter.content.iter().map(Latex::as_str).collect();
+
+            let mut ctx = Context::new();
+            ctx.insert("title", &chapter.title);
+            ctx.insert("content", &content);
+
+            self.dir.write_template(
+                &PathBuf::from(format!("chapters/{}.tex", idx)),
+                tera,
+                "latex/chapter.tex",
+                &ctx,
+            )?;
+        }
+
+        Ok(())
+    }
+
+    /// Write a LaTeX project for a print edition of the book: a `book.tex`
+    /// document including one file per chapter, to be compiled with
+    /// LuaLaTeX.  JPEG and PNG covers are given a page of their own.
+    pub fn generate_latex(
+        &mut self,
+        project: &Project<Cover, Latex>,
+        assets: &dyn Assets,
+        templates: Option<&Path>,
+    ) -> Result<(), Error> {
+        let mut tera = load_templates(assets, templates)?;
+
+        tera.register_filter("roman", roman_filter);
+        tera.register_filter("latex", latex_filter);
+
+        let cover = match project.cover {
+            Some(ref cov) => match cov.media_type()? {
+                "image/jpeg" | "image/png" => {
+                    let path = format!("cover.{}", cov.extension);
+                    self.dir
+                        .write_bytes(&PathBuf::from(&path), cov.content.as_slice())?;
+                    Some(path)
+                }
+                _ => None,
+            },
+            None => None,
+        };
+
+        let font = match project.fonts {
+            Some(ref fonts) => match fonts.first() {
+                Some(family) => self.install_font(assets, family)?,
+                None => None,
+            },
+            None => self.install_font(assets, &FontFamily::defaults()[0])?,
+        };
+
+        let chapters = project.content.chapters();
+
+        self.generate_chapters(&tera, &chapters)?;
+
+        let isbn = match project
+            .metadata
+            .as_ref()
+            .and_then(|m| m.identifier.as_ref())
+        {
+            Some(Identifier::Isbn(isbn)) => Some(isbn),
+            _ => None,
+        };
+
+        let mut ctx = Context::new();
+        ctx.insert("title", &project.title);
+        ctx.insert("author", &project.author);
+        ctx.insert("language", &project.language);
+        ctx.insert("numbering", &project.numbering.unwrap_or(false));
+        ctx.insert("metadata", &project.metadata);
+        ctx.insert("isbn", &isbn);
+        ctx.insert("cover", &cover);
+        ctx.insert("font", &font);
+        insert_contents(&mut ctx, &project.content, |idx, _| idx);
+
+        self.dir
+            .write_template(&PathBuf::from("book.tex"), &tera, "latex/book.tex", &ctx)?;
+        self.dir.write_template(
+            &PathBuf::from("latexmkrc"),
+            &tera,
+            "latex/latexmkrc",
+            &Context::new(),
+        )?;
+
+        Ok(())
+    }
+}
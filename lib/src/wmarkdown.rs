@@ -0,0 +1,109 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use tera::{Context, Error as TError, Result as TResult, Tera, Value};
+
+use crate::assets::{load_templates, Assets};
+use crate::error::Error;
+use crate::markdown::{escape_markdown, Markdown};
+use crate::project::{Cover, Project};
+use crate::utils::roman_filter;
+use crate::writer::{chapter_summary, insert_contents, BookWriter, DirectoryWriter};
+
+fn markdown_filter(value: &Value, _kargs: &HashMap<String, Value>) -> TResult<Value> {
+    let text = value.as_str().ok_or(TError::msg("Expected string"))?;
+
+    Ok(Value::String(escape_markdown(text)))
+}
+
+pub struct MarkdownWriter {
+    dir: DirectoryWriter,
+}
+
+impl MarkdownWriter {
+    pub fn init(base: &Path) -> Result<MarkdownWriter, Error> {
+        Ok(MarkdownWriter {
+            dir: DirectoryWriter::init(base)?,
+        })
+    }
+
+    fn generate_index(
+        &mut self,
+        project: &Project<Cover, Markdown>,
+        tera: &Tera,
+        cover: &Option<String>,
+    ) -> Result<(), Error> {
+        let mut ctx = Context::new();
+
+        insert_contents(&mut ctx, &project.content, chapter_summary);
+
+        ctx.insert("numbering", &project.numbering);
+        ctx.insert("language", &project.language);
+        ctx.insert("title", &project.title);
+        ctx.insert("author", &project.author);
+        ctx.insert("description", &project.description);
+        ctx.insert("metadata", &project.metadata);
+        ctx.insert("cover", cover);
+
+        self.dir
+            .write_template(&PathBuf::from("index.md"), tera, "markdown/index.md", &ctx)
+    }
+
+    fn generate_chapters(
+        &mut self,
+        project: &Project<Cover, Markdown>,
+        tera: &Tera,
+    ) -> Result<(), Error> {
+        let chapters = project.content.chapters();
+        let max = chapters.len();
+
+        for (idx, chapter) in chapters.into_iter().enumerate() {
+            let content: Vec<&str> = chapter.content.iter().map(Markdown::as_str).collect();
+
+            let mut ctx = Context::new();
+            ctx.insert("number", &(idx + 1));
+            ctx.insert("title", &chapter.title);
+            ctx.insert("content", &content);
+            ctx.insert("numbering", &project.numbering.unwrap_or(false));
+            ctx.insert("language", &project.language);
+            ctx.insert("previous", &idx.checked_sub(1));
+            ctx.insert("next", &Some(idx + 1).filter(|next| *next < max));
+
+            self.dir.write_template(
+                &PathBuf::from(format!("{}.md", idx)),
+                tera,
+                "markdown/chapter.md",
+                &ctx,
+            )?;
+        }
+
+        Ok(())
+    }
+
+    /// Write one Markdown file per chapter, and an index listing them.
+    pub fn generate_markdown(
+        &mut self,
+        project: &Project<Cover, Markdown>,
+        assets: &dyn Assets,
+        templates: Option<&Path>,
+    ) -> Result<(), Error> {
+        let mut tera = load_templates(assets, templates)?;
+
+        tera.register_filter("roman", roman_filter);
+        tera.register_filter("markdown", markdown_filter);
+
+        let cover = match project.cover {
+            Some(ref cov) => {
+                let path = format!("cover.{}", cov.extension);
+                self.dir
+                    .write_bytes(&PathBuf::from(&path), cov.content.as_slice())?;
+                Some(path)
+            }
+            None => None,
+        };
+
+        self.generate_index(project, &tera, &cover)?;
+        self.generate_chapters(project, &tera)?;
+
+        Ok(())
+    }
+}
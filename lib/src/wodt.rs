@@ -0,0 +1,90 @@
+use std::path::{Path, PathBuf};
+use tera::{Context, Tera};
+
+use crate::assets::{load_templates, Assets};
+use crate::epub::{build_timestamp, format_timestamp};
+use crate::error::Error;
+use crate::odt::Odt;
+use crate::project::{Content, Cover, Project};
+use crate::utils::roman_filter;
+use crate::writer::{insert_contents, BookWriter};
+
+const ODT_MIMETYPE: &str = "application/vnd.oasis.opendocument.text";
+
+/// Render each chapter of the book with its heading, as OpenDocument
+/// paragraphs.
+fn render_chapters(project: &Project<Cover, Odt>, tera: &Tera) -> Result<Vec<String>, Error> {
+    project
+        .content
+        .chapters()
+        .iter()
+        .enumerate()
+        .map(|(idx, chapter)| {
+            let content: Vec<&str> = chapter.content.iter().map(Odt::as_str).collect();
+
+            let mut ctx = Context::new();
+            ctx.insert("number", &(idx + 1));
+            ctx.insert("title", &chapter.title);
+            ctx.insert("content", &content);
+            ctx.insert("numbering", &project.numbering.unwrap_or(false));
+            ctx.insert("parts", &matches!(project.content, Content::WithParts(_)));
+
+            tera.render("odt/chapter.xml", &ctx)
+                .map_err(|e| Error::template(Some("odt/chapter.xml"), e))
+        })
+        .collect()
+}
+
+pub trait OdtWriter: BookWriter {
+    fn create_manifest(&mut self, tera: &Tera) -> Result<(), Error> {
+        self.write_bytes(&PathBuf::from("mimetype"), ODT_MIMETYPE.as_bytes())?;
+        self.write_template(
+            &PathBuf::from("META-INF/manifest.xml"),
+            tera,
+            "odt/manifest.xml",
+            &Context::new(),
+        )
+    }
+
+    /// Write an OpenDocument text of the book, for LibreOffice.  The
+    /// paragraphs use the `Story`, `Aside` and `SceneBreak` styles, and the
+    /// replies the `Dialogue`, `Reply` and `Thought` character styles.
+    fn generate_odt(
+        &mut self,
+        project: &Project<Cover, Odt>,
+        assets: &dyn Assets,
+        templates: Option<&Path>,
+    ) -> Result<(), Error> {
+        let mut tera = load_templates(assets, templates)?;
+
+        tera.register_filter("roman", roman_filter);
+
+        self.create_manifest(&tera)?;
+
+        let chapters = render_chapters(project, &tera)?;
+
+        let mut ctx = Context::new();
+
+        insert_contents(&mut ctx, &project.content, |idx, _| &chapters[idx]);
+
+        ctx.insert("title", &project.title);
+        ctx.insert("author", &project.author);
+        ctx.insert("description", &project.description);
+        ctx.insert("numbering", &project.numbering.unwrap_or(false));
+        ctx.insert("language", &project.language);
+        ctx.insert("modified", &format_timestamp(build_timestamp()?));
+
+        self.write_template(
+            &PathBuf::from("content.xml"),
+            &tera,
+            "odt/content.xml",
+            &ctx,
+        )?;
+        self.write_template(&PathBuf::from("styles.xml"), &tera, "odt/styles.xml", &ctx)?;
+        self.write_template(&PathBuf::from("meta.xml"), &tera, "odt/meta.xml", &ctx)?;
+
+        Ok(())
+    }
+}
+
+impl<W> OdtWriter for W where W: BookWriter {}
@@ -1,24 +1,130 @@
-use std::path::PathBuf;
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::fs::{copy, create_dir, create_dir_all, write};
+use std::path::{Path, PathBuf};
 use tera::{Context, Tera};
 
 use crate::error::Error;
+use crate::project::{Chapter, Content};
 
 pub trait BookWriter {
-    fn write_file(&mut self, dst: &PathBuf, src: &PathBuf) -> Result<(), Error>;
+    fn write_file(&mut self, dst: &Path, src: &Path) -> Result<(), Error>;
 
-    fn write_bytes(&mut self, dst: &PathBuf, input: &[u8]) -> Result<(), Error>;
+    fn write_bytes(&mut self, dst: &Path, input: &[u8]) -> Result<(), Error>;
 
     fn write_template(
         &mut self,
-        dst: &PathBuf,
+        dst: &Path,
         tera: &Tera,
         template: &str,
         ctx: &Context,
     ) -> Result<(), Error> {
         let content = tera
             .render(template, ctx)
-            .map_err(|err| Error::new(&format!("{:?}", err)))?;
+            .map_err(|err| Error::template(Some(template), err))?;
 
         self.write_bytes(dst, content.as_bytes())
     }
 }
+
+/// Write the files of a book in a directory of the file system.
+pub struct DirectoryWriter {
+    base: PathBuf,
+}
+
+impl DirectoryWriter {
+    /// Write in `base`, which is created if it does not exist.
+    pub fn init(base: &Path) -> Result<DirectoryWriter, Error> {
+        if !base.exists() {
+            create_dir(base).map_err(|e| Error::io("create directory", base, e))?;
+        }
+
+        if base.is_dir() {
+            Ok(DirectoryWriter {
+                base: base.to_owned(),
+            })
+        } else {
+            Err(Error::new(&format!(
+                "{:?} already exists and is not a directory",
+                base
+            )))
+        }
+    }
+
+    /// The path of `dst` in the directory, whose parent directories are
+    /// created if needed.
+    fn prepare(&self, dst: &Path) -> Result<PathBuf, Error> {
+        let path = self.base.join(dst);
+
+        if let Some(parent) = path.parent() {
+            create_dir_all(parent).map_err(|e| Error::io("create directory", parent, e))?;
+        }
+
+        Ok(path)
+    }
+}
+
+impl BookWriter for DirectoryWriter {
+    fn write_bytes(&mut self, dst: &Path, input: &[u8]) -> Result<(), Error> {
+        let path = self.prepare(dst)?;
+
+        write(&path, input).map_err(|e| Error::io("write", path, e))
+    }
+
+    fn write_file(&mut self, dst: &Path, src: &Path) -> Result<(), Error> {
+        let path = self.prepare(dst)?;
+
+        copy(src, &path).map_err(|e| Error::io("copy", src, e))?;
+
+        Ok(())
+    }
+}
+
+/// Insert the table of contents of a book in `ctx`: either its `parts`, with
+/// their `index`, `title` and `chapters`, or its `chapters`.  `entry` gives
+/// what the templates know of a chapter, from its index in the book.
+pub fn insert_contents<I, T, F>(ctx: &mut Context, content: &Content<I>, mut entry: F)
+where
+    T: Serialize,
+    F: FnMut(usize, &Chapter<I>) -> T,
+{
+    match content {
+        Content::WithParts(ref parts) => {
+            let mut ofs = 0;
+            let mut acc = vec![];
+
+            for (idx, part) in parts.iter().enumerate() {
+                let chapters: Vec<T> = (ofs..)
+                    .zip(&part.content)
+                    .map(|(idx, chapter)| entry(idx, chapter))
+                    .collect();
+
+                acc.push(json!({
+                    "index": idx,
+                    "title": part.title,
+                    "chapters": chapters,
+                }));
+
+                ofs += part.content.len();
+            }
+
+            ctx.insert("parts", &acc);
+        }
+        Content::WithChapters(ref chapters) => {
+            let chapters: Vec<T> = (0..)
+                .zip(chapters)
+                .map(|(idx, chapter)| entry(idx, chapter))
+                .collect();
+
+            ctx.insert("chapters", &chapters);
+        }
+    }
+}
+
+/// The index and the title of a chapter, for the templates to link to it.
+pub fn chapter_summary<I>(idx: usize, chapter: &Chapter<I>) -> Value {
+    json!({
+        "index": idx,
+        "title": chapter.title,
+    })
+}
@@ -1,113 +1,57 @@
 use serde_json::json;
-use std::collections::HashMap;
-use std::fs::{create_dir, read_to_string};
-use std::path::PathBuf;
-use tera::{Context, Error as TError, Result as TResult, Tera, Value};
-
-use crate::assets::template_dir;
-use crate::error::{Error, Raise};
-use crate::project::{Chapter, Cover, Language, Project};
+use std::path::{Path, PathBuf};
+use tera::{Context, Tera, Value};
+
+use crate::assets::{load_templates, template_file, Assets};
+use crate::characters::stylesheet;
+use crate::error::Error;
+use crate::fonts::FontFamily;
+use crate::project::{Chapter, Cover, EpubVersion, Language, Project};
 use crate::render::Html;
-use crate::{BookWriter, Content, Part};
-
-fn roman_filter(value: &Value, _kargs: &HashMap<String, Value>) -> TResult<Value> {
-    let result = roman::to(
-        value
-            .as_i64()
-            .map(|x| x as i32)
-            .ok_or(TError::msg("Expected integer"))?,
-    )
-    .ok_or(TError::msg("Could not convert to roman notation"))?;
-
-    Ok(Value::String(result))
+use crate::subset::{subset_font, used_chars};
+use crate::utils::{base64, roman_filter};
+use crate::writer::{chapter_summary, insert_contents, BookWriter, DirectoryWriter};
+use crate::{Content, Part};
+
+/// Encode `data` as a `data:` URI.
+fn data_uri(media_type: &str, data: &[u8]) -> String {
+    format!("data:{};base64,{}", media_type, base64(data))
 }
 
 pub struct Static {
-    base: PathBuf,
+    dir: DirectoryWriter,
     body_only: bool,
 }
 
-impl BookWriter for Static {
-    fn write_bytes(&mut self, dst: &PathBuf, input: &[u8]) -> Result<(), Error> {
-        std::fs::write(&self.base.join(dst), input)
-            .or_raise(&format!("Could not write content to file {:?}", dst))?;
-
-        Ok(())
-    }
-
-    fn write_file(&mut self, dst: &PathBuf, src: &PathBuf) -> Result<(), Error> {
-        let input =
-            read_to_string(src).or_raise(&format!("Could not read content of file {:?}", src))?;
-
-        self.write_bytes(dst, input.as_bytes())?;
-
-        Ok(())
-    }
-}
-
 impl Static {
-    pub fn init(base: &PathBuf, body_only: bool) -> Result<Static, Error> {
-        if !base.exists() {
-            create_dir(base).or_raise("Could not create output directory.")?;
-        }
-
-        if base.is_dir() {
-            Ok(Static {
-                base: base.to_owned(),
-                body_only: body_only,
-            })
-        } else {
-            Err(Error::new(&format!(
-                "{:?} already exists and is not a directory",
-                base
-            )))
-        }
+    pub fn init(base: &Path, body_only: bool) -> Result<Static, Error> {
+        Ok(Static {
+            dir: DirectoryWriter::init(base)?,
+            body_only,
+        })
     }
 
-    fn generate_index(&mut self, project: &Project<Cover, Html>, tera: &Tera) -> Result<(), Error> {
-        fn make_chaps(chapters: &Vec<Chapter<Html>>, idx_ofs: usize) -> Vec<serde_json::Value> {
-            chapters
-                .iter()
-                .enumerate()
-                .map(|(idx, chapter)| {
-                    json!({
-                        "index": idx + idx_ofs,
-                        "title": chapter.title,
-                    })
-                })
-                .collect()
-        }
-
+    fn generate_index(
+        &mut self,
+        project: &Project<Cover, Html>,
+        tera: &Tera,
+        social: &Value,
+    ) -> Result<(), Error> {
         let mut ctx = Context::new();
 
-        match project.content {
-            Content::WithParts(ref parts) => {
-                let mut acc = vec![];
-                let (_, _) = parts.iter().fold((0, 0), |(part_idx, chap_idx), p| {
-                    let part_json = json!({
-                        "index": part_idx,
-                        "title": p.title,
-                        "chapters": make_chaps(&p.content, chap_idx)
-                    });
-                    acc.push(part_json);
-
-                    (part_idx + 1, chap_idx + p.content.len())
-                });
-                ctx.insert("parts", &acc);
-            }
-            Content::WithChapters(ref chaps) => {
-                let chaps: Vec<_> = make_chaps(chaps, 0);
-                ctx.insert("chapters", &chaps);
-            }
-        };
+        insert_contents(&mut ctx, &project.content, chapter_summary);
 
         ctx.insert("numbering", &project.numbering);
         ctx.insert("language", &project.language);
         ctx.insert("title", &project.title);
+        ctx.insert("author", &project.author);
         ctx.insert("body_only", &self.body_only);
         ctx.insert("description", &project.description);
+        ctx.insert("metadata", &project.metadata);
+        ctx.insert("characters", &project.characters);
+        ctx.insert("social", social);
 
-        self.write_template(
+        self.dir.write_template(
             &PathBuf::from("index.html"),
             tera,
             "static/index.html",
@@ -117,15 +61,17 @@ impl Static {
         Ok(())
     }
 
+    #[allow(clippy::too_many_arguments)]
     fn generate_chapters(
         &mut self,
         tera: &Tera,
-        chapters: &Vec<Chapter<Html>>,
+        chapters: &[Chapter<Html>],
         offset: usize,
         numbering: bool,
         lang: &Language,
         previous_part: Option<usize>,
         next_part: Option<usize>,
+        social: &Value,
     ) -> Result<(), Error> {
         let max = chapters.len();
 
@@ -143,10 +89,14 @@ impl Static {
                 ctx.insert("chapters_number", &max);
                 ctx.insert("previous_part", &previous_part);
                 ctx.insert("next_part", &next_part);
+                ctx.insert("social", social);
+                ctx.insert("page_title", &c.title);
 
                 let path: PathBuf = PathBuf::from(format!("{}.html", idx + offset));
+                ctx.insert("page_path", &path);
 
-                self.write_template(&path, tera, "static/chapter.html", &ctx)?;
+                self.dir
+                    .write_template(&path, tera, "static/chapter.html", &ctx)?;
 
                 Ok(())
             })
@@ -158,13 +108,14 @@ impl Static {
     pub fn generate_parts(
         &mut self,
         tera: &Tera,
-        parts: &Vec<Part<Html>>,
+        parts: &[Part<Html>],
         numbering: bool,
         lang: &Language,
+        social: &Value,
     ) -> Result<(), Error> {
         let mut ofs = 0;
 
-        for (idx, part) in parts.into_iter().enumerate() {
+        for (idx, part) in parts.iter().enumerate() {
             let next_part = if idx + 1 < parts.len() {
                 Some(idx + 1)
             } else {
@@ -180,10 +131,14 @@ impl Static {
             ctx.insert("chapters_number", &part.content.len());
             ctx.insert("parts_number", &parts.len());
             ctx.insert("offset", &ofs);
+            ctx.insert("social", social);
+            ctx.insert("page_title", &part.title);
 
             let path: PathBuf = PathBuf::from(format!("p{}.html", idx));
+            ctx.insert("page_path", &path);
 
-            self.write_template(&path, tera, "static/part.html", &ctx)?;
+            self.dir
+                .write_template(&path, tera, "static/part.html", &ctx)?;
 
             self.generate_chapters(
                 tera,
@@ -193,6 +148,7 @@ impl Static {
                 lang,
                 Some(idx),
                 next_part,
+                social,
             )?;
 
             ofs += part.content.len();
@@ -207,11 +163,14 @@ impl Static {
         content: &Content<Html>,
         numbering: bool,
         lang: &Language,
+        social: &Value,
     ) -> Result<(), Error> {
         match content {
-            Content::WithParts(ref parts) => self.generate_parts(tera, parts, numbering, lang)?,
+            Content::WithParts(ref parts) => {
+                self.generate_parts(tera, parts, numbering, lang, social)?
+            }
             Content::WithChapters(ref chapters) => {
-                self.generate_chapters(tera, chapters, 0, numbering, lang, None, None)?
+                self.generate_chapters(tera, chapters, 0, numbering, lang, None, None, social)?
             }
         }
         Ok(())
@@ -220,29 +179,173 @@ impl Static {
     pub fn generate_static_website(
         &mut self,
         project: &Project<Cover, Html>,
-        assets: &PathBuf,
+        assets: &dyn Assets,
+        templates: Option<&Path>,
     ) -> Result<(), Error> {
-        let mut tera =
-            Tera::new(template_dir(assets)?.as_str()).or_raise("Could not build templates")?;
+        let mut tera = load_templates(assets, templates)?;
 
         tera.register_filter("roman", roman_filter);
 
-        self.generate_index(project, &tera)?;
+        let url = project
+            .metadata
+            .as_ref()
+            .and_then(|m| m.url.as_ref())
+            .map(|url| url.trim_end_matches('/'));
+
+        let cover = match project.cover {
+            Some(ref cov) => {
+                let path = format!("cover.{}", cov.extension);
+                self.dir
+                    .write_bytes(&PathBuf::from(&path), cov.content.as_slice())?;
+                Some(path)
+            }
+            None => None,
+        };
+
+        // Social networks expect absolute URLs for previews, so we can only
+        // rely on relative paths when the address of the website is unknown.
+        let absolute = |path: &str| match url {
+            Some(url) => format!("{}/{}", url, path),
+            None => String::from(path),
+        };
+
+        let social = json!({
+            "title": project.title,
+            "author": project.author,
+            "description": project.description,
+            "url": url,
+            "image": cover.as_ref().map(|c| absolute(c)),
+            "cover": cover,
+        });
+
+        self.generate_index(project, &tera, &social)?;
 
         self.generate_content(
             &tera,
             &project.content,
             project.numbering.unwrap_or(false),
             &project.language,
+            &social,
         )?;
 
         if !self.body_only {
-            self.write_file(
-                &PathBuf::from("style.css"),
-                &assets.join(PathBuf::from("templates/static/style.css")),
-            )?;
+            let mut style = template_file(assets, templates, "static/style.css")?;
+
+            if let Some(ref characters) = project.characters {
+                style.extend(stylesheet(characters)?.into_bytes());
+            }
+
+            self.dir.write_bytes(&PathBuf::from("style.css"), &style)?;
         }
 
         Ok(())
     }
+
+    /// Inline the fonts of the book as `data:` URIs, reduced to the glyphs
+    /// the book uses if the project asks for it.
+    fn inline_fonts(
+        project: &Project<Cover, Html>,
+        assets: &dyn Assets,
+        families: &[FontFamily],
+    ) -> Result<Vec<Value>, Error> {
+        let chars = if project.subset_fonts.unwrap_or(false) {
+            Some(used_chars(project))
+        } else {
+            None
+        };
+
+        let mut res = vec![];
+
+        for family in families {
+            for face in &family.faces {
+                let media_type = face.media_type(&EpubVersion::Epub3)?;
+                let mut data = face.load(assets)?;
+
+                if let Some(ref chars) = chars {
+                    if !media_type.contains("woff") {
+                        data = subset_font(&data, chars).map_err(|e| Error::Other {
+                            message: format!("Could not subset {:?}", face.file),
+                            source: Some(Box::new(e)),
+                        })?;
+                    }
+                }
+
+                res.push(json!({
+                    "family": family.family,
+                    "weight": face.weight,
+                    "style": face.style,
+                    "uri": data_uri(media_type, &data),
+                }));
+            }
+        }
+
+        Ok(res)
+    }
+
+    /// Write the whole book in the single HTML file `name`, with its
+    /// stylesheet, cover and fonts inlined, so that it can be shared or
+    /// printed as is.
+    pub fn generate_single_page(
+        &mut self,
+        project: &Project<Cover, Html>,
+        assets: &dyn Assets,
+        templates: Option<&Path>,
+        name: &Path,
+    ) -> Result<(), Error> {
+        let mut tera = load_templates(assets, templates)?;
+
+        tera.register_filter("roman", roman_filter);
+
+        let mut ctx = Context::new();
+
+        insert_contents(&mut ctx, &project.content, |idx, chapter| {
+            json!({
+                "index": idx,
+                "title": chapter.title,
+                "content": chapter.content,
+            })
+        });
+
+        let mut style = String::from_utf8(template_file(assets, templates, "static/style.css")?)
+            .map_err(|e| Error::Other {
+                message: String::from("static/style.css is not valid UTF-8"),
+                source: Some(Box::new(e)),
+            })?;
+
+        if let Some(ref characters) = project.characters {
+            style.push_str(&stylesheet(characters)?);
+        }
+
+        let default_fonts;
+        let families = match project.fonts {
+            Some(ref fonts) => fonts,
+            None => {
+                default_fonts = FontFamily::defaults();
+                &default_fonts
+            }
+        };
+
+        let cover = match project.cover {
+            Some(ref cov) => Some(data_uri(cov.media_type()?, &cov.content)),
+            None => None,
+        };
+
+        ctx.insert("numbering", &project.numbering);
+        ctx.insert("language", &project.language);
+        ctx.insert("title", &project.title);
+        ctx.insert("author", &project.author);
+        ctx.insert("description", &project.description);
+        ctx.insert("metadata", &project.metadata);
+        ctx.insert("characters", &project.characters);
+        ctx.insert("style", &style);
+        ctx.insert("fonts", &Static::inline_fonts(project, assets, families)?);
+        ctx.insert(
+            "families",
+            &families.iter().map(|f| &f.family).collect::<Vec<_>>(),
+        );
+        ctx.insert("cover", &cover);
+
+        self.dir
+            .write_template(name, &tera, "static/book.html", &ctx)
+    }
 }
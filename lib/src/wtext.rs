@@ -0,0 +1,179 @@
+use std::path::{Path, PathBuf};
+use tera::{Context, Tera};
+
+use crate::assets::{load_templates, Assets};
+use crate::error::Error;
+use crate::project::{Cover, Project};
+use crate::text::Text;
+use crate::utils::roman_filter;
+use crate::writer::{insert_contents, BookWriter, DirectoryWriter};
+
+/// Wrap the lines of `text` at `width` characters, keeping their
+/// indentation.  Lines are only broken at regular spaces, and words longer
+/// than `width` are left as is.
+fn wrap(text: &str, width: usize) -> String {
+    let mut res = String::with_capacity(text.len());
+
+    for line in text.lines() {
+        let content = line.trim_start_matches(' ');
+        let indent = &line[..line.len() - content.len()];
+        let mut current = 0;
+
+        for word in content.split(' ').filter(|w| !w.is_empty()) {
+            let len = word.chars().count();
+
+            if current == 0 {
+                res.push_str(indent);
+                current = indent.len();
+            } else if current + 1 + len > width {
+                res.push('\n');
+                res.push_str(indent);
+                current = indent.len();
+            } else {
+                res.push(' ');
+                current += 1;
+            }
+
+            res.push_str(word);
+            current += len;
+        }
+
+        res.push('\n');
+    }
+
+    res
+}
+
+pub struct TextWriter {
+    dir: DirectoryWriter,
+    /// The maximum length of the lines, if they should be wrapped
+    width: Option<usize>,
+}
+
+impl TextWriter {
+    pub fn init(base: &Path, width: Option<usize>) -> Result<TextWriter, Error> {
+        Ok(TextWriter {
+            dir: DirectoryWriter::init(base)?,
+            width,
+        })
+    }
+
+    /// Render each chapter of the book with its heading.
+    fn render_chapters(
+        &self,
+        project: &Project<Cover, Text>,
+        tera: &Tera,
+    ) -> Result<Vec<String>, Error> {
+        project
+            .content
+            .chapters()
+            .iter()
+            .enumerate()
+            .map(|(idx, chapter)| {
+                let content: Vec<String> = chapter
+                    .content
+                    .iter()
+                    .map(|doc| match self.width {
+                        Some(width) => wrap(doc.as_str().trim_end(), width),
+                        None => format!("{}\n", doc.as_str().trim_end()),
+                    })
+                    .collect();
+
+                let mut ctx = Context::new();
+                ctx.insert("number", &(idx + 1));
+                ctx.insert("title", &chapter.title);
+                ctx.insert("content", &content);
+                ctx.insert("numbering", &project.numbering.unwrap_or(false));
+                ctx.insert("language", &project.language);
+
+                tera.render("text/chapter.txt", &ctx)
+                    .map_err(|e| Error::template(Some("text/chapter.txt"), e))
+            })
+            .collect()
+    }
+
+    fn load_templates(assets: &dyn Assets, templates: Option<&Path>) -> Result<Tera, Error> {
+        let mut tera = load_templates(assets, templates)?;
+
+        tera.register_filter("roman", roman_filter);
+
+        Ok(tera)
+    }
+
+    /// Write the whole book in the file `name`.
+    pub fn generate_book(
+        &mut self,
+        project: &Project<Cover, Text>,
+        assets: &dyn Assets,
+        templates: Option<&Path>,
+        name: &Path,
+    ) -> Result<(), Error> {
+        let tera = TextWriter::load_templates(assets, templates)?;
+        let chapters = self.render_chapters(project, &tera)?;
+
+        let mut ctx = Context::new();
+
+        insert_contents(&mut ctx, &project.content, |idx, _| &chapters[idx]);
+
+        ctx.insert("title", &project.title);
+        ctx.insert("author", &project.author);
+        ctx.insert("numbering", &project.numbering.unwrap_or(false));
+        ctx.insert("language", &project.language);
+
+        self.dir.write_template(name, &tera, "text/book.txt", &ctx)
+    }
+
+    /// Write one file per chapter, named after its index.
+    pub fn generate_chapters(
+        &mut self,
+        project: &Project<Cover, Text>,
+        assets: &dyn Assets,
+        templates: Option<&Path>,
+    ) -> Result<(), Error> {
+        let tera = TextWriter::load_templates(assets, templates)?;
+
+        for (idx, chapter) in self.render_chapters(project, &tera)?.iter().enumerate() {
+            self.dir
+                .write_bytes(&PathBuf::from(format!("{}.txt", idx)), chapter.as_bytes())?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::wrap;
+
+    #[test]
+    fn wraps_at_spaces() {
+        assert_eq!(
+            wrap("the quick brown fox jumps", 10),
+            "the quick\nbrown fox\njumps\n"
+        );
+    }
+
+    #[test]
+    fn keeps_long_words() {
+        assert_eq!(
+            wrap("a extraordinarily long word", 8),
+            "a\nextraordinarily\nlong\nword\n"
+        );
+    }
+
+    #[test]
+    fn keeps_indentation() {
+        assert_eq!(
+            wrap("    the quick brown fox\nthe end", 14),
+            "    the quick\n    brown fox\nthe end\n"
+        );
+    }
+
+    #[test]
+    fn joins_words_at_non_breaking_spaces() {
+        assert_eq!(
+            wrap("il dit\u{a0}: «\u{a0}non\u{a0}»", 7),
+            "il\ndit\u{a0}:\n«\u{a0}non\u{a0}»\n"
+        );
+    }
+}